@@ -1,23 +1,120 @@
+use std::fs;
 use crate::point::Point;
-use crate::pes::PES;
+use crate::pes::PotentialEnergySurface;
+use crate::optimizer::{Optimizer, OptimizerConfig};
+use crate::minimizer::{MinimizerConfig, Minimization};
 
+///how the elements of the chain are moved in every iteration
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    ///the gradient perpendicular to the line between the neighbors plus springs towards their middle
+    #[default]
+    Legacy,
+    ///nudged elastic band with the improved (upwinding) tangent
+    Neb,
+    ///zero temperature string method: move along the full gradient, then redistribute the elements
+    /// at equal arc length. Needs no springs
+    String,
+    ///string method that starts from the two ends only and grows towards the middle from both sides
+    /// before continuing as a normal string
+    GrowingString,
+}
 
-#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ChainConfig {
     pub(crate) spring_constant: f64,
     pub(crate) pin_ends: bool,
+    ///if ends that aren't pinned slide along the contour line of their starting energy, pulled by the
+    /// spring towards their neighbor (free end NEB). Otherwise they follow the force perpendicular to
+    /// the chain
+    #[serde(default)]
+    pub(crate) free_ends: bool,
     pub(crate) relax_ends: bool,
     pub(crate) start: Point,
     pub(crate) end: Point,
     pub(crate) elements: usize,
+    #[serde(default)]
+    pub(crate) method: Method,
+    ///after this many iterations the highest element stops feeling the springs and climbs up along
+    /// the tangent, so it converges onto the saddle point
+    #[serde(default)]
+    pub(crate) climbing_image: Option<usize>,
+    ///how many iterations pass between adding new elements to the growing string
+    #[serde(default = "default_growth_interval")]
+    pub(crate) growth_interval: usize,
+    #[serde(default)]
+    pub(crate) optimizer: OptimizerConfig,
+    ///how the ends are relaxed into their minima
+    #[serde(default)]
+    pub(crate) minimizer: MinimizerConfig,
+    ///points the initial path passes through on its way from start to end
+    #[serde(default)]
+    pub(crate) waypoints: Vec<Point>,
+    ///a JSON or CSV file with the waypoints, used instead of the ones given above
+    #[serde(default)]
+    pub(crate) path_file: Option<String>,
+    ///if the initial path is a smooth spline through the waypoints instead of straight lines
+    #[serde(default)]
+    pub(crate) spline: bool,
+}
+
+fn default_growth_interval() -> usize {
+    10
 }
 
 impl ChainConfig {
-    pub fn relax_ends(&mut self, pes: &PES, convergence_limit: f64) {
-        if self.relax_ends {
-            self.start.move_to_minimum(pes, convergence_limit);
-            self.end.move_to_minimum(pes, convergence_limit);
+    ///rejects values the simulation can't work with
+    pub fn check(&self) -> Result<(), String> {
+        if self.growth_interval == 0 {
+            return Err("growth_interval has to be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    ///replaces the waypoints with the ones from the path file, if there is one. A file ending in .csv
+    /// has one "x,y" pair per line, only the first line may be a header without numbers and empty
+    /// lines are skipped. Anything else is read as a JSON list of points
+    pub fn load_path_file(&mut self) -> Result<(), String> {
+        let file = match &self.path_file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        let data = fs::read_to_string(file).map_err(|err| err.to_string())?;
+        let waypoints: Vec<Point> = if file.ends_with(".csv") {
+            let mut waypoints = Vec::new();
+            for (i, line) in data.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let mut values = line.split(',').map(|value| value.trim().parse::<f64>());
+                match (values.next(), values.next(), values.next()) {
+                    (Some(Ok(x)), Some(Ok(y)), None) => waypoints.push(Point { x, y }),
+                    // a header names the columns, a first line with numbers in it is a broken point
+                    _ if i == 0 && line.split(',').all(|value| value.trim().parse::<f64>().is_err()) => {}
+                    _ => return Err(format!("line {} is not an \"x,y\" pair: {}", i + 1, line)),
+                }
+            }
+            waypoints
+        } else {
+            serde_json::de::from_str(&data).map_err(|err| err.to_string())?
+        };
+        if waypoints.is_empty() {
+            return Err("the file contains no points".to_string());
+        }
+        self.waypoints = waypoints;
+        Ok(())
+    }
+
+    ///moves start and end into their minima, if relax_ends is set. Reports how that went for both
+    pub fn relax_ends(&mut self, pes: &dyn PotentialEnergySurface, convergence_limit: f64) -> Option<[Minimization; 2]> {
+        if !self.relax_ends {
+            return None;
         }
+        Some([
+            self.start.move_to_minimum(pes, self.optimizer, &self.minimizer, convergence_limit),
+            self.end.move_to_minimum(pes, self.optimizer, &self.minimizer, convergence_limit),
+        ])
     }
 }
 
@@ -25,76 +122,639 @@ impl ChainConfig {
 pub struct Chain {
     pub(crate) config: ChainConfig,
     pub(crate) elements: Vec<Point>,
+    #[serde(default)]
+    pub(crate) iteration: usize,
+    ///while the string is still growing: how many of the elements belong to the half grown from the
+    /// start. The rest is the half grown from the end
+    #[serde(default)]
+    pub(crate) growing: Option<usize>,
+    pub(crate) optimizer: Optimizer,
 }
 
 impl Chain {
     pub(crate) fn new(config: ChainConfig) -> Self {
-        let x = config.start.x;
-        let y = config.start.y;
-        let dx = (config.end.x - config.start.x) / config.elements as f64;
-        let dy = (config.end.y - config.start.y) / config.elements as f64;
-        let mut elements = Vec::with_capacity(config.elements);
-        for i in 0..=config.elements {
-            elements.push(Point { x: x + dx * i as f64, y: y + dy * i as f64 })
+        // a growing string starts from its ends only, it doesn't need an initial guess
+        if config.method == Method::GrowingString {
+            return Chain {
+                elements: vec![config.start, config.end],
+                iteration: 0,
+                growing: Some(1),
+                optimizer: Optimizer::new(config.optimizer),
+                config,
+            };
         }
+
+        // the initial path goes from start through all waypoints to the end,
+        // the elements are spread evenly along it
+        let mut path = vec![config.start];
+        path.extend(config.waypoints.iter());
+        path.push(config.end);
+        if config.spline {
+            path = catmull_rom(&path, 20);
+        }
+        let lengths = arc_lengths(&path);
+        let total = lengths[lengths.len() - 1];
+        let elements = (0..=config.elements)
+            .map(|i| point_at_arc_length(&path, &lengths, total * i as f64 / config.elements as f64))
+            .collect();
         Chain {
-            config,
             elements,
+            iteration: 0,
+            growing: None,
+            optimizer: Optimizer::new(config.optimizer),
+            config,
         }
     }
 
-    pub fn energy(&self, pes: &PES) -> f64 {
+    pub fn energy(&self, pes: &dyn PotentialEnergySurface) -> f64 {
         let total_energy: f64 = self.elements.iter().map(|&p| pes.energy_at(p)).sum();
-        total_energy / self.config.elements as f64
+        total_energy / self.elements.len() as f64
+    }
+
+    ///index of the element with the highest energy, not counting the two ends
+    pub fn highest_image(&self, pes: &dyn PotentialEnergySurface) -> usize {
+        let mut highest = 0;
+        for i in 1..self.elements.len() - 1 {
+            if highest == 0 || pes.energy_at(self.elements[i]) > pes.energy_at(self.elements[highest]) {
+                highest = i;
+            }
+        }
+        highest
+    }
+
+    ///index of the climbing image, if it is already active
+    pub fn climbing_image(&self, pes: &dyn PotentialEnergySurface) -> Option<usize> {
+        if self.growing.is_some() {
+            return None;
+        }
+        match self.config.climbing_image {
+            Some(start) if self.iteration >= start && self.elements.len() > 2 => Some(self.highest_image(pes)),
+            _ => None,
+        }
+    }
+
+    ///the element closest to the saddle point: the climbing image if active, the highest one otherwise
+    pub fn saddle_image(&self, pes: &dyn PotentialEnergySurface) -> usize {
+        self.climbing_image(pes).unwrap_or_else(|| self.highest_image(pes))
+    }
+
+    ///the part of the true force on every element that the band can't relax by moving along itself.
+    /// The climbing image feels the full force, pinned ends none at all and free ends the pull along
+    /// their contour line
+    pub fn perpendicular_forces(&self, pes: &dyn PotentialEnergySurface) -> Vec<Point> {
+        let size = self.elements.len();
+        let energies: Vec<f64> = self.elements.iter().map(|&p| pes.energy_at(p)).collect();
+        let climbing_image = self.climbing_image(pes);
+        (0..size).map(|i| {
+            let gradient = pes.gradient_at(self.elements[i]);
+            if i == 0 || i == size - 1 {
+                if self.config.pin_ends || size < 2 {
+                    Point { x: 0.0, y: 0.0 }
+                } else if self.config.free_ends {
+                    self.contour_force(pes, i)
+                } else {
+                    let neighbor = if i == 0 { self.elements[1] } else { self.elements[size - 2] };
+                    perpendicular_part(gradient, (neighbor - self.elements[i]).normed())
+                }
+            } else if climbing_image == Some(i) {
+                gradient
+            } else {
+                perpendicular_part(gradient, self.tangent(i, &energies))
+            }
+        }).collect()
+    }
+
+    pub fn iterate(&mut self, pes: &dyn PotentialEnergySurface) {
+        if let Some(split) = self.growing {
+            self.iterate_growing(pes, split);
+            self.iteration += 1;
+            return;
+        }
+
+        let mut forces = match self.config.method {
+            Method::Legacy => self.legacy_forces(pes),
+            Method::Neb => self.neb_forces(pes),
+            Method::String | Method::GrowingString => self.string_forces(pes),
+        };
+
+        // the climbing image moves up along the tangent and down in every other direction
+        let climbing_image = self.climbing_image(pes);
+        if let Some(i) = climbing_image {
+            let energies: Vec<f64> = self.elements.iter().map(|&p| pes.energy_at(p)).collect();
+            let tangent = self.tangent(i, &energies);
+            let gradient = pes.gradient_at(self.elements[i]);
+            forces[i] = gradient - 2.0 * gradient.dot_product(tangent) * tangent;
+        }
+        self.free_end_forces(pes, &mut forces);
+
+        self.optimizer.step(&mut self.elements, &forces);
+        self.keep_ends_on_contour(pes);
+
+        if let Method::String | Method::GrowingString = self.config.method {
+            // a climbing image stays where it is, the parts on either side of it are spaced separately
+            match climbing_image {
+                Some(i) => {
+                    redistribute(&mut self.elements[..=i]);
+                    redistribute(&mut self.elements[i..]);
+                }
+                None => redistribute(&mut self.elements),
+            }
+        }
+        self.iteration += 1;
+    }
+
+    ///replaces the forces on the ends with the pull along their contour line, if they are free
+    fn free_end_forces(&self, pes: &dyn PotentialEnergySurface, forces: &mut [Point]) {
+        if self.config.pin_ends || !self.config.free_ends {
+            return;
+        }
+        let last = self.elements.len() - 1;
+        forces[0] = self.contour_force(pes, 0);
+        forces[last] = self.contour_force(pes, last);
+    }
+
+    ///the spring towards the neighbor of an end, without the part along the gradient. The end can only
+    /// follow the contour line, and settles where the chain leaves it along the steepest ascent
+    fn contour_force(&self, pes: &dyn PotentialEnergySurface, i: usize) -> Point {
+        let this = self.elements[i];
+        let neighbor = if i == 0 { self.elements[1] } else { self.elements[self.elements.len() - 2] };
+        let gradient = pes.gradient_at(this);
+        if gradient.norm() == 0.0 {
+            // a contour line through a stationary point has no direction to follow
+            return Point { x: 0.0, y: 0.0 };
+        }
+        let spring = self.config.spring_constant * (neighbor - this);
+        perpendicular_part(spring, gradient.normed())
+    }
+
+    ///a step along the contour line drifts off it where the line is curved. This moves free ends back
+    /// onto the energy of the start and end point they came from
+    fn keep_ends_on_contour(&mut self, pes: &dyn PotentialEnergySurface) {
+        if self.config.pin_ends || !self.config.free_ends {
+            return;
+        }
+        let last = self.elements.len() - 1;
+        self.elements[0] = onto_contour(self.elements[0], pes, pes.energy_at(self.config.start));
+        self.elements[last] = onto_contour(self.elements[last], pes, pes.energy_at(self.config.end));
     }
 
-    pub fn iterate(&mut self, pes: &PES) {
+    ///one iteration of the growing string. Both halves relax like a string. Every growth_interval
+    /// iterations both get a new element at their tip, until the gap between them is closed
+    fn iterate_growing(&mut self, pes: &dyn PotentialEnergySurface, split: usize) {
         let size = self.elements.len();
-        let mut next_instance = Vec::with_capacity(size);
+        let spacing = (self.config.end - self.config.start).norm() / self.config.elements as f64;
+
+        // the tips only move perpendicular to the gap, otherwise they would slide back down
+        let gap = (self.elements[split] - self.elements[split - 1]).normed();
+        let mut forces = self.string_forces(pes);
+        for &tip in [split - 1, split].iter() {
+            if tip != 0 && tip != size - 1 {
+                forces[tip] = perpendicular_part(forces[tip], gap);
+            }
+        }
+        self.free_end_forces(pes, &mut forces);
+        self.optimizer.step(&mut self.elements, &forces);
+        self.keep_ends_on_contour(pes);
+        redistribute(&mut self.elements[..split]);
+        redistribute(&mut self.elements[split..]);
+
+        if !self.iteration.is_multiple_of(self.config.growth_interval) {
+            return;
+        }
+
+        let start_tip = self.elements[split - 1];
+        let end_tip = self.elements[split];
+        if (end_tip - start_tip).norm() <= 2.0 * spacing {
+            // close the gap and continue as a normal string with the configured number of elements
+            let lengths = arc_lengths(&self.elements);
+            let total = lengths[lengths.len() - 1];
+            let elements = self.config.elements;
+            self.elements = (0..=elements)
+                .map(|i| point_at_arc_length(&self.elements, &lengths, total * i as f64 / elements as f64))
+                .collect();
+            self.growing = None;
+            return;
+        }
+
+        // grow along the direction the relaxed half is already heading, bent towards the other half
+        // so the two of them are sure to meet
+        let start_direction = if split >= 2 {
+            (start_tip - self.elements[split - 2]).normed() + (end_tip - start_tip).normed()
+        } else {
+            end_tip - start_tip
+        };
+        let end_direction = if split + 1 < size {
+            (end_tip - self.elements[split + 1]).normed() + (start_tip - end_tip).normed()
+        } else {
+            start_tip - end_tip
+        };
+        self.elements.insert(split, start_tip + spacing * start_direction.normed());
+        self.elements.insert(split + 1, end_tip + spacing * end_direction.normed());
+        self.growing = Some(split + 1);
+    }
+
+    ///the normed tangent at every element, pointing from the start towards the end
+    pub fn tangents(&self, pes: &dyn PotentialEnergySurface) -> Vec<Point> {
+        let size = self.elements.len();
+        let energies: Vec<f64> = self.elements.iter().map(|&p| pes.energy_at(p)).collect();
+        (0..size).map(|i| {
+            if i == 0 {
+                (self.elements[1] - self.elements[0]).normed()
+            } else if i == size - 1 {
+                (self.elements[i] - self.elements[i - 1]).normed()
+            } else {
+                self.tangent(i, &energies)
+            }
+        }).collect()
+    }
+
+    ///the normed tangent of the band at an element that has two neighbors
+    fn tangent(&self, i: usize, energies: &[f64]) -> Point {
+        let prev = self.elements[i - 1];
+        let next = self.elements[i + 1];
+        match self.config.method {
+            Method::Legacy | Method::String | Method::GrowingString => (next - prev).normed(),
+            Method::Neb => upwind_tangent(
+                prev, self.elements[i], next,
+                energies[i - 1], energies[i], energies[i + 1],
+            ),
+        }
+    }
+
+    ///how far every element moves with the original scheme: the gradient perpendicular to the line
+    /// between the two neighbors plus a spring pulling towards the middle of the neighbors
+    fn legacy_forces(&self, pes: &dyn PotentialEnergySurface) -> Vec<Point> {
+        let size = self.elements.len();
+        let mut forces = Vec::with_capacity(size);
 
         // start point
         if self.config.pin_ends {
-            next_instance.push(*self.elements.get(0).unwrap());
+            forces.push(Point { x: 0.0, y: 0.0 });
         } else {
-            let this = *self.elements.get(0).unwrap();
+            let this = *self.elements.first().unwrap();
             let next = *self.elements.get(1).unwrap();
-            next_instance.push(
-                this.move_perpendicular_to(
-                    this,
-                    next,
-                    pes.gradient_at(this),
-                    0.0,
-                )
-            );
+            forces.push(this.move_perpendicular_to(this, next, pes.gradient_at(this), 0.0) - this);
         }
 
+        // the springs used to move the elements directly, before there were optimizers. Dividing by the
+        // configured step size keeps that with steepest descent, while a reduced step still damps them
+        let spring_constant = match self.config.optimizer {
+            OptimizerConfig::SteepestDescent { step_size } if step_size > 0.0 => self.config.spring_constant / step_size,
+            _ => self.config.spring_constant,
+        };
+
         // all the points that have two neighbors. Start and end need to be treated separately
         for i in 1..size - 1 {
             let prev = *self.elements.get(i - 1).unwrap();
             let this = *self.elements.get(i).unwrap();
             let next = *self.elements.get(i + 1).unwrap();
-            next_instance.push(this.move_perpendicular_to(
+            forces.push(this.move_perpendicular_to(
                 prev,
                 next,
                 pes.gradient_at(this),
-                self.config.spring_constant,
-            ));
+                spring_constant,
+            ) - this);
         }
 
         // end point
         if self.config.pin_ends {
-            next_instance.push(*self.elements.get(size - 1).unwrap());
+            forces.push(Point { x: 0.0, y: 0.0 });
         } else {
             let prev = *self.elements.get(size - 2).unwrap();
             let this = *self.elements.get(size - 1).unwrap();
-            next_instance.push(this.move_perpendicular_to(
-                prev,
-                this,
-                pes.gradient_at(this),
-                0.0,
-            ));
+            forces.push(this.move_perpendicular_to(prev, this, pes.gradient_at(this), 0.0) - this);
         }
 
-        self.elements = next_instance;
+        forces
     }
-}
\ No newline at end of file
+
+    ///nudged elastic band: the true force perpendicular to the upwinding tangent plus a spring force
+    /// parallel to it that keeps the elements equally spaced
+    fn neb_forces(&self, pes: &dyn PotentialEnergySurface) -> Vec<Point> {
+        let size = self.elements.len();
+        let energies: Vec<f64> = self.elements.iter().map(|&p| pes.energy_at(p)).collect();
+        let mut forces = Vec::with_capacity(size);
+
+        for i in 0..size {
+            let this = self.elements[i];
+            if i == 0 || i == size - 1 {
+                if self.config.pin_ends {
+                    forces.push(Point { x: 0.0, y: 0.0 });
+                } else {
+                    // the ends only have one neighbor to take a tangent from and feel no springs
+                    let neighbor = if i == 0 { self.elements[1] } else { self.elements[size - 2] };
+                    let tangent = (neighbor - this).normed();
+                    forces.push(perpendicular_part(pes.gradient_at(this), tangent));
+                }
+                continue;
+            }
+
+            let prev = self.elements[i - 1];
+            let next = self.elements[i + 1];
+            let tangent = self.tangent(i, &energies);
+            let spring = self.config.spring_constant * ((next - this).norm() - (this - prev).norm());
+            forces.push(perpendicular_part(pes.gradient_at(this), tangent) + spring * tangent);
+        }
+
+        forces
+    }
+
+    ///string method: every element that is allowed to move follows the full gradient
+    fn string_forces(&self, pes: &dyn PotentialEnergySurface) -> Vec<Point> {
+        let size = self.elements.len();
+        (0..size).map(|i| {
+            if self.config.pin_ends && (i == 0 || i == size - 1) {
+                Point { x: 0.0, y: 0.0 }
+            } else {
+                pes.gradient_at(self.elements[i])
+            }
+        }).collect()
+    }
+}
+
+///the distance along the path from the first point to every point
+pub(crate) fn arc_lengths(points: &[Point]) -> Vec<f64> {
+    let mut lengths = Vec::with_capacity(points.len());
+    let mut length = 0.0;
+    for i in 0..points.len() {
+        if i > 0 {
+            length += (points[i] - points[i - 1]).norm();
+        }
+        lengths.push(length);
+    }
+    lengths
+}
+
+///the point that lies at the given distance along the path, interpolating linearly between the points
+pub(crate) fn point_at_arc_length(points: &[Point], lengths: &[f64], s: f64) -> Point {
+    let segment = match lengths.iter().position(|&l| l > s) {
+        Some(0) => return points[0],
+        Some(i) => i,
+        None => return points[points.len() - 1],
+    };
+    let fraction = (s - lengths[segment - 1]) / (lengths[segment] - lengths[segment - 1]);
+    points[segment - 1] + fraction * (points[segment] - points[segment - 1])
+}
+
+///a Catmull-Rom spline through all the points, sampled with the given number of points per segment
+fn catmull_rom(points: &[Point], samples: usize) -> Vec<Point> {
+    let size = points.len();
+    let mut curve = Vec::with_capacity((size - 1) * samples + 1);
+    for i in 0..size - 1 {
+        // the ends are continued by repeating the outermost points
+        let p0 = points[if i == 0 { 0 } else { i - 1 }];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[if i + 2 < size { i + 2 } else { size - 1 }];
+        for j in 0..samples {
+            let t = j as f64 / samples as f64;
+            let t2 = t * t;
+            let t3 = t2 * t;
+            curve.push(0.5 * (2.0 * p1
+                + t * (p2 - p0)
+                + t2 * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3)
+                + t3 * (3.0 * p1 - p0 - 3.0 * p2 + p3)));
+        }
+    }
+    curve.push(points[size - 1]);
+    curve
+}
+
+///moves the points along the path they describe so they are evenly spaced. The ends stay fixed
+fn redistribute(points: &mut [Point]) {
+    let lengths = arc_lengths(points);
+    let total = lengths[lengths.len() - 1];
+    let old = points.to_vec();
+    let size = points.len();
+    for (i, p) in points.iter_mut().enumerate().take(size - 1).skip(1) {
+        *p = point_at_arc_length(&old, &lengths, total * i as f64 / (size - 1) as f64);
+    }
+}
+
+///how many Newton steps are taken at most to get back onto a contour line
+const CONTOUR_STEPS: usize = 20;
+
+///moves p along the gradient until its energy is the given one, using Newton steps on the energy
+fn onto_contour(mut p: Point, pes: &dyn PotentialEnergySurface, energy: f64) -> Point {
+    for _ in 0..CONTOUR_STEPS {
+        let force = pes.gradient_at(p);
+        let force_sq = force.dot_product(force);
+        let error = pes.energy_at(p) - energy;
+        if force_sq == 0.0 || error.abs() <= 1e-12 * energy.abs().max(1.0) {
+            break;
+        }
+        // the force is the negative gradient, so following it lowers the energy
+        p += (error / force_sq) * force;
+    }
+    p
+}
+
+///the part of the vector that is perpendicular to the (normed) tangent
+fn perpendicular_part(v: Point, tangent: Point) -> Point {
+    v - v.dot_product(tangent) * tangent
+}
+
+///the tangent at an element of the band, taken towards the neighbor with the higher energy.
+/// At extrema along the band both neighbors are mixed, weighted by the energy differences, so the
+/// tangent turns smoothly. See Henkelman and Jónsson, J. Chem. Phys. 113, 9978 (2000)
+fn upwind_tangent(prev: Point, this: Point, next: Point, e_prev: f64, e_this: f64, e_next: f64) -> Point {
+    let tangent_plus = next - this;
+    let tangent_minus = this - prev;
+    let tangent = if e_next > e_this && e_this > e_prev {
+        tangent_plus
+    } else if e_next < e_this && e_this < e_prev {
+        tangent_minus
+    } else {
+        let delta_max = (e_next - e_this).abs().max((e_prev - e_this).abs());
+        let delta_min = (e_next - e_this).abs().min((e_prev - e_this).abs());
+        if e_next > e_prev {
+            delta_max * tangent_plus + delta_min * tangent_minus
+        } else {
+            delta_min * tangent_plus + delta_max * tangent_minus
+        }
+    };
+    tangent.normed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muller_brown::{MullerBrown, MINIMA, SADDLES};
+
+    ///a band of 20 elements between two of the Müller-Brown minima, relaxed with L-BFGS
+    fn muller_brown_config(method: Method, start: usize, end: usize) -> ChainConfig {
+        ChainConfig {
+            spring_constant: 200.0,
+            pin_ends: true,
+            free_ends: false,
+            relax_ends: false,
+            start: MINIMA[start].0,
+            end: MINIMA[end].0,
+            elements: 20,
+            method,
+            climbing_image: None,
+            growth_interval: default_growth_interval(),
+            optimizer: OptimizerConfig::Lbfgs { memory: 10, max_step: 0.02 },
+            minimizer: MinimizerConfig::default(),
+            waypoints: vec![],
+            path_file: None,
+            spline: false,
+        }
+    }
+
+    ///iterates until the chain is done growing, the climbing image is active and no perpendicular force
+    /// is above 1e-3, but at most max_iterations times
+    fn relax(config: ChainConfig, max_iterations: usize) -> Chain {
+        let mut chain = Chain::new(config);
+        for _ in 0..max_iterations {
+            chain.iterate(&MullerBrown);
+            let max_force = chain.perpendicular_forces(&MullerBrown).iter().map(|f| f.norm()).fold(0.0, f64::max);
+            let climbing = chain.config.climbing_image.is_none_or(|start| chain.iteration > start);
+            if chain.growing.is_none() && climbing && max_force < 1e-3 {
+                break;
+            }
+        }
+        chain
+    }
+
+    ///the distance between neighboring elements if they were spread evenly along the chain
+    fn spacing(chain: &Chain) -> f64 {
+        arc_lengths(&chain.elements)[chain.elements.len() - 1] / (chain.elements.len() - 1) as f64
+    }
+
+    #[test]
+    fn upwind_tangent_points_to_the_higher_neighbor() {
+        let prev = Point { x: 0.0, y: 0.0 };
+        let this = Point { x: 1.0, y: 0.0 };
+        let next = Point { x: 1.0, y: 1.0 };
+        let uphill = upwind_tangent(prev, this, next, 0.0, 1.0, 2.0);
+        assert!(uphill.distance_sq(Point { x: 0.0, y: 1.0 }) < 1e-20);
+        let downhill = upwind_tangent(prev, this, next, 2.0, 1.0, 0.0);
+        assert!(downhill.distance_sq(Point { x: 1.0, y: 0.0 }) < 1e-20);
+        // at a maximum both neighbors count, the higher one more
+        let maximum = upwind_tangent(prev, this, next, 0.0, 3.0, 1.0);
+        assert!(maximum.y > maximum.x && maximum.x > 0.0);
+    }
+
+    #[test]
+    fn neb_passes_by_the_muller_brown_saddle() {
+        let chain = relax(muller_brown_config(Method::Neb, 0, 2), 3000);
+
+        let spacing = spacing(&chain);
+        for pair in chain.elements.windows(2) {
+            assert!(((pair[1] - pair[0]).norm() - spacing).abs() < 1e-3 * spacing);
+        }
+        // without a climbing image the highest element is only the closest one to the saddle
+        let (position, energy) = SADDLES[0];
+        let highest = chain.elements[chain.highest_image(&MullerBrown)];
+        assert!(highest.distance_sq(position).sqrt() < spacing / 2.0, "highest at ({}|{})", highest.x, highest.y);
+        assert!(MullerBrown.energy_at(highest) < energy + 1e-6);
+        assert!(MullerBrown.energy_at(highest) > energy - 1.0);
+    }
+
+    #[test]
+    fn climbing_image_finds_the_muller_brown_saddle() {
+        let config = ChainConfig { climbing_image: Some(50), ..muller_brown_config(Method::Neb, 0, 2) };
+        let chain = relax(config, 3000);
+
+        let (position, energy) = SADDLES[0];
+        let saddle = chain.elements[chain.saddle_image(&MullerBrown)];
+        assert!(saddle.distance_sq(position).sqrt() < 1e-4, "saddle at ({}|{})", saddle.x, saddle.y);
+        assert!((MullerBrown.energy_at(saddle) - energy).abs() < 1e-4);
+    }
+
+    #[test]
+    fn climbing_image_finds_the_second_muller_brown_saddle() {
+        let config = ChainConfig { climbing_image: Some(50), ..muller_brown_config(Method::Neb, 2, 1) };
+        let chain = relax(config, 3000);
+
+        let (position, energy) = SADDLES[1];
+        let saddle = chain.elements[chain.saddle_image(&MullerBrown)];
+        assert!(saddle.distance_sq(position).sqrt() < 1e-4, "saddle at ({}|{})", saddle.x, saddle.y);
+        assert!((MullerBrown.energy_at(saddle) - energy).abs() < 1e-4);
+    }
+
+    #[test]
+    fn string_passes_by_the_muller_brown_saddle() {
+        // the reparametrization moves the elements after every step, which throws off L-BFGS
+        let config = ChainConfig {
+            optimizer: OptimizerConfig::SteepestDescent { step_size: 1e-4 },
+            ..muller_brown_config(Method::String, 0, 2)
+        };
+        let chain = relax(config, 2000);
+
+        assert!(chain.elements[0].distance_sq(MINIMA[0].0) == 0.0);
+        assert!(chain.elements[20].distance_sq(MINIMA[2].0) == 0.0);
+        let spacing = spacing(&chain);
+        for pair in chain.elements.windows(2) {
+            assert!(((pair[1] - pair[0]).norm() - spacing).abs() < 0.02 * spacing);
+        }
+        let (position, energy) = SADDLES[0];
+        let highest = chain.elements[chain.highest_image(&MullerBrown)];
+        assert!(highest.distance_sq(position).sqrt() < spacing / 2.0, "highest at ({}|{})", highest.x, highest.y);
+        assert!((MullerBrown.energy_at(highest) - energy).abs() < 1.0);
+    }
+
+    #[test]
+    fn growing_string_closes_the_gap_at_the_muller_brown_saddle() {
+        let config = ChainConfig {
+            optimizer: OptimizerConfig::SteepestDescent { step_size: 1e-4 },
+            ..muller_brown_config(Method::GrowingString, 0, 2)
+        };
+        let chain = relax(config, 2000);
+
+        assert!(chain.growing.is_none());
+        assert_eq!(chain.elements.len(), 21);
+        let spacing = spacing(&chain);
+        let (position, energy) = SADDLES[0];
+        let highest = chain.elements[chain.highest_image(&MullerBrown)];
+        assert!(highest.distance_sq(position).sqrt() < spacing / 2.0, "highest at ({}|{})", highest.x, highest.y);
+        assert!((MullerBrown.energy_at(highest) - energy).abs() < 1.0);
+    }
+
+    #[test]
+    fn growth_interval_zero_is_rejected() {
+        let config = ChainConfig { growth_interval: 0, ..muller_brown_config(Method::GrowingString, 0, 2) };
+        assert!(config.check().is_err());
+        assert!(muller_brown_config(Method::GrowingString, 0, 2).check().is_ok());
+    }
+
+    #[test]
+    fn onto_contour_reaches_the_energy() {
+        let energy = -120.0;
+        for &offset in [Point { x: 0.1, y: 0.0 }, Point { x: 0.0, y: -0.15 }, Point { x: -0.1, y: 0.1 }].iter() {
+            let p = onto_contour(MINIMA[0].0 + offset, &MullerBrown, energy);
+            assert!((MullerBrown.energy_at(p) - energy).abs() < 1e-9, "{} at ({}|{})", MullerBrown.energy_at(p), p.x, p.y);
+        }
+    }
+
+    #[test]
+    fn free_ends_stay_on_their_contour_lines() {
+        let config = ChainConfig {
+            pin_ends: false,
+            free_ends: true,
+            start: MINIMA[0].0 + Point { x: 0.1, y: 0.05 },
+            end: MINIMA[2].0 + Point { x: 0.0, y: -0.08 },
+            climbing_image: Some(50),
+            ..muller_brown_config(Method::Neb, 0, 2)
+        };
+        let (start, end) = (config.start, config.end);
+        let start_energy = MullerBrown.energy_at(start);
+        let end_energy = MullerBrown.energy_at(end);
+        let chain = relax(config, 3000);
+
+        let last = chain.elements.len() - 1;
+        assert!((MullerBrown.energy_at(chain.elements[0]) - start_energy).abs() < 1e-9);
+        assert!((MullerBrown.energy_at(chain.elements[last]) - end_energy).abs() < 1e-9);
+        // the ends moved to where the path leaves their contour lines along the gradient
+        assert!(chain.elements[0].distance_sq(start).sqrt() > 0.1);
+        assert!(chain.elements[last].distance_sq(end).sqrt() > 0.01);
+        for &i in [0, last].iter() {
+            assert!(chain.contour_force(&MullerBrown, i).norm() < 1e-3);
+        }
+        let (position, energy) = SADDLES[0];
+        let saddle = chain.elements[chain.saddle_image(&MullerBrown)];
+        assert!(saddle.distance_sq(position).sqrt() < 1e-4, "saddle at ({}|{})", saddle.x, saddle.y);
+        assert!((MullerBrown.energy_at(saddle) - energy).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,66 @@
+use std::fs;
+use crate::chain::Chain;
+use crate::relaxation::LoopState;
+use crate::image::ImageConfig;
+
+///where and how often the state of the simulation is saved, so it can be resumed later
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CheckpointConfig {
+    ///save every this many iterations. Null disables the checkpoints
+    #[serde(default = "default_interval")]
+    pub(crate) interval: Option<usize>,
+    #[serde(default = "default_file")]
+    pub(crate) file: String,
+}
+
+fn default_interval() -> Option<usize> {
+    Some(100)
+}
+
+fn default_file() -> String {
+    String::from("checkpoint.json")
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        CheckpointConfig {
+            interval: default_interval(),
+            file: default_file(),
+        }
+    }
+}
+
+///everything needed to continue a simulation exactly where it stopped. The chain carries its
+/// iteration counter and the state of its optimizer with it, the rest of the loop is in state
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Checkpoint {
+    ///hash of the parts of the config that influence the simulation, see config_hash
+    pub(crate) config_hash: u64,
+    ///the image area of the original config, the stability check keeps using it after resuming
+    pub(crate) domain: ImageConfig,
+    pub(crate) state: LoopState,
+    pub(crate) chain: Chain,
+}
+
+impl Checkpoint {
+    pub fn save(&self, file: &str) -> Result<(), String> {
+        let data = serde_json::ser::to_string(self).map_err(|err| err.to_string())?;
+        fs::write(file, data).map_err(|err| err.to_string())
+    }
+
+    pub fn load(file: &str) -> Result<Self, String> {
+        let data = fs::read_to_string(file).map_err(|err| err.to_string())?;
+        serde_json::de::from_str(&data).map_err(|err| err.to_string())
+    }
+}
+
+///64 bit FNV-1a hash of the given text. Unlike the hasher of the standard library it is guaranteed to
+/// stay the same between builds, so checkpoints can be checked against the config they came from
+pub fn config_hash(text: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+use crate::point::Point;
+
+///additional reasons to stop the simulation, next to the change in average energy
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct ConvergenceConfig {
+    ///stop once the change in average energy stays below the convergence_limit, up or down
+    #[serde(default = "default_energy")]
+    pub(crate) energy: bool,
+    ///for how many iterations in a row the energy has to barely change, so a single small step
+    /// or an oscillation that happens to cross over doesn't end the simulation
+    #[serde(default = "default_energy_iterations")]
+    pub(crate) energy_iterations: usize,
+    ///stop once no element feels a perpendicular force larger than this
+    #[serde(default)]
+    pub(crate) max_force: Option<f64>,
+    ///stop once the root mean square of the perpendicular forces drops below this
+    #[serde(default)]
+    pub(crate) rms_force: Option<f64>,
+    ///stop once no element moved further than this in the last iteration
+    #[serde(default)]
+    pub(crate) max_displacement: Option<f64>,
+    ///stop after this many iterations no matter what. Only null lets an oscillating chain run forever
+    #[serde(default = "default_max_iterations")]
+    pub(crate) max_iterations: Option<usize>,
+}
+
+fn default_energy() -> bool {
+    true
+}
+
+fn default_energy_iterations() -> usize {
+    5
+}
+
+fn default_max_iterations() -> Option<usize> {
+    Some(1000)
+}
+
+impl Default for ConvergenceConfig {
+    fn default() -> Self {
+        ConvergenceConfig {
+            energy: true,
+            energy_iterations: default_energy_iterations(),
+            max_force: None,
+            rms_force: None,
+            max_displacement: None,
+            max_iterations: default_max_iterations(),
+        }
+    }
+}
+
+///what happened in the last iteration
+pub struct Progress<'a> {
+    pub(crate) iteration: usize,
+    ///how many iterations in a row, including this one, the energy changed by less than the
+    /// convergence_limit
+    pub(crate) calm_iterations: usize,
+    pub(crate) perpendicular_forces: &'a [Point],
+    pub(crate) max_displacement: f64,
+    ///a growing string is not done until both of its halves have met
+    pub(crate) growing: bool,
+}
+
+impl<'a> Progress<'a> {
+    pub fn max_force(&self) -> f64 {
+        self.perpendicular_forces.iter().map(|f| f.norm()).fold(0.0, f64::max)
+    }
+
+    pub fn rms_force(&self) -> f64 {
+        let sum: f64 = self.perpendicular_forces.iter().map(|f| f.dot_product(*f)).sum();
+        (sum / self.perpendicular_forces.len() as f64).sqrt()
+    }
+}
+
+///how far the element that moved the most went. Infinite if elements were added or removed
+pub fn max_displacement(previous: &[Point], current: &[Point]) -> f64 {
+    if previous.len() != current.len() {
+        return f64::INFINITY;
+    }
+    previous.iter().zip(current).map(|(&a, &b)| (b - a).norm()).fold(0.0, f64::max)
+}
+
+///counts the iterations in a row in which the energy changed by less than the convergence_limit.
+/// Rising energy counts as a change just like falling energy
+pub fn calm_iterations(previous: usize, energy_change: f64, convergence_limit: f64) -> usize {
+    if energy_change.abs() < convergence_limit {
+        previous + 1
+    } else {
+        0
+    }
+}
+
+///the criterion that ended the simulation
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Criterion {
+    Energy,
+    MaxForce,
+    RmsForce,
+    MaxDisplacement,
+    MaxIterations,
+}
+
+impl fmt::Display for Criterion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Criterion::Energy => "the average energy barely changed for energy_iterations iterations in a row",
+            Criterion::MaxForce => "the largest perpendicular force is below max_force",
+            Criterion::RmsForce => "the root mean square perpendicular force is below rms_force",
+            Criterion::MaxDisplacement => "no element moved further than max_displacement",
+            Criterion::MaxIterations => "max_iterations reached",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl ConvergenceConfig {
+    ///the first criterion that is met, if any
+    pub fn check(&self, progress: &Progress) -> Option<Criterion> {
+        if let Some(max_iterations) = self.max_iterations {
+            if progress.iteration >= max_iterations {
+                return Some(Criterion::MaxIterations);
+            }
+        }
+        if progress.growing {
+            return None;
+        }
+        if let Some(max_force) = self.max_force {
+            if progress.max_force() < max_force {
+                return Some(Criterion::MaxForce);
+            }
+        }
+        if let Some(rms_force) = self.rms_force {
+            if progress.rms_force() < rms_force {
+                return Some(Criterion::RmsForce);
+            }
+        }
+        if let Some(max_displacement) = self.max_displacement {
+            if progress.max_displacement < max_displacement {
+                return Some(Criterion::MaxDisplacement);
+            }
+        }
+        if self.energy && progress.calm_iterations >= self.energy_iterations.max(1) {
+            return Some(Criterion::Energy);
+        }
+        None
+    }
+}
@@ -0,0 +1,187 @@
+use std::f64::consts::FRAC_PI_2;
+use crate::point::Point;
+use crate::pes::PotentialEnergySurface;
+use crate::optimizer::{Optimizer, OptimizerConfig};
+
+///how the saddle point found by the chain is refined with the dimer method
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct DimerConfig {
+    ///distance between the center of the dimer and its end
+    #[serde(default = "default_separation")]
+    pub(crate) separation: f64,
+    ///stop once the force at the center is smaller than this and the curvature is negative
+    #[serde(default = "default_max_force")]
+    pub(crate) max_force: f64,
+    ///give up after this many translations
+    #[serde(default = "default_max_iterations")]
+    pub(crate) max_iterations: usize,
+    ///how often the dimer may be rotated before each translation
+    #[serde(default = "default_max_rotations")]
+    pub(crate) max_rotations: usize,
+    ///rotations smaller than this angle (in radians) count as converged
+    #[serde(default = "default_rotation_tolerance")]
+    pub(crate) rotation_tolerance: f64,
+    ///moves the center of the dimer, the optimizer of the path is used if left out
+    #[serde(default)]
+    pub(crate) optimizer: Option<OptimizerConfig>,
+}
+
+fn default_separation() -> f64 {
+    1e-3
+}
+
+fn default_max_force() -> f64 {
+    1e-6
+}
+
+fn default_max_iterations() -> usize {
+    1000
+}
+
+fn default_max_rotations() -> usize {
+    4
+}
+
+fn default_rotation_tolerance() -> f64 {
+    1e-3
+}
+
+impl Default for DimerConfig {
+    fn default() -> Self {
+        DimerConfig {
+            separation: default_separation(),
+            max_force: default_max_force(),
+            max_iterations: default_max_iterations(),
+            max_rotations: default_max_rotations(),
+            rotation_tolerance: default_rotation_tolerance(),
+            optimizer: None,
+        }
+    }
+}
+
+///two points close together around a center. Turning the dimer into the direction of lowest
+/// curvature and moving the center uphill along it and downhill in all other directions converges to
+/// a first order saddle point without ever needing the hessian
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct Dimer {
+    pub(crate) center: Point,
+    ///normed, ends up along the mode of lowest curvature
+    pub(crate) orientation: Point,
+    ///the curvature of the energy along the orientation
+    pub(crate) curvature: f64,
+    pub(crate) force: f64,
+    pub(crate) iterations: usize,
+    pub(crate) converged: bool,
+}
+
+impl Dimer {
+    ///refines the saddle point starting at center with the dimer pointing along orientation, usually
+    /// the tangent of the path
+    pub fn refine(pes: &dyn PotentialEnergySurface, center: Point, orientation: Point,
+                  config: &DimerConfig, path_optimizer: OptimizerConfig) -> Self {
+        let mut dimer = Dimer {
+            center,
+            orientation: orientation.normed(),
+            curvature: 0.0,
+            force: 0.0,
+            iterations: 0,
+            converged: false,
+        };
+        let mut optimizer = Optimizer::new(config.optimizer.unwrap_or(path_optimizer));
+        let mut positions = [center];
+        loop {
+            let force = pes.gradient_at(dimer.center);
+            dimer.rotate(pes, force, config);
+            dimer.force = force.norm();
+            if dimer.force < config.max_force && dimer.curvature < 0.0 {
+                dimer.converged = true;
+                break;
+            }
+            if dimer.iterations >= config.max_iterations {
+                break;
+            }
+            optimizer.step(&mut positions, &[dimer.effective_force(force)]);
+            dimer.center = positions[0];
+            dimer.iterations += 1;
+        }
+        dimer
+    }
+
+    ///the curvature along direction from the force at its end, the force at the center is known.
+    /// Also returns the difference of the forces at both ends, the one at the far end is estimated
+    fn curvature_along(&self, pes: &dyn PotentialEnergySurface, force: Point, direction: Point, separation: f64) -> (f64, Point) {
+        let end = pes.gradient_at(self.center + separation * direction);
+        let difference = 2.0 * (end - force);
+        (-difference.dot_product(direction) / (2.0 * separation), difference)
+    }
+
+    ///turns the dimer towards the direction of lowest curvature. The curvature is a sum of cos(2φ)
+    /// and sin(2φ) in the angle, so one trial rotation is enough to find its minimum
+    fn rotate(&mut self, pes: &dyn PotentialEnergySurface, force: Point, config: &DimerConfig) {
+        let separation = config.separation;
+        for _ in 0..config.max_rotations {
+            let (curvature, difference) = self.curvature_along(pes, force, self.orientation, separation);
+            self.curvature = curvature;
+            let rotational = difference - difference.dot_product(self.orientation) * self.orientation;
+            if rotational.norm() == 0.0 {
+                break;
+            }
+            let direction = rotational.normed();
+            let derivative = -difference.dot_product(direction) / separation;
+            let trial_angle = -0.5 * (derivative / (2.0 * curvature.abs())).atan();
+            if trial_angle.abs() < config.rotation_tolerance {
+                break;
+            }
+            let trial = turned(self.orientation, direction, trial_angle);
+            let (trial_curvature, _) = self.curvature_along(pes, force, trial, separation);
+
+            let b = 0.5 * derivative;
+            let a = (curvature - trial_curvature + b * (2.0 * trial_angle).sin()) / (1.0 - (2.0 * trial_angle).cos());
+            let along = |angle: f64| curvature - a + a * (2.0 * angle).cos() + b * (2.0 * angle).sin();
+            let mut angle = 0.5 * (b / a).atan();
+            if along(angle) > curvature {
+                angle += FRAC_PI_2;
+            }
+            self.orientation = turned(self.orientation, direction, angle);
+            self.curvature = along(angle);
+            if angle.abs() < config.rotation_tolerance {
+                break;
+            }
+        }
+    }
+
+    ///the force with its component along the dimer inverted, so the center climbs along the lowest
+    /// mode. While the curvature is still positive only that uphill part is used
+    fn effective_force(&self, force: Point) -> Point {
+        let parallel = force.dot_product(self.orientation) * self.orientation;
+        if self.curvature < 0.0 {
+            force - 2.0 * parallel
+        } else {
+            -1.0 * parallel
+        }
+    }
+}
+
+///rotates the normed vector by the angle in the plane spanned with the normed, perpendicular direction
+fn turned(orientation: Point, direction: Point, angle: f64) -> Point {
+    (angle.cos() * orientation + angle.sin() * direction).normed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muller_brown::{MullerBrown, SADDLES};
+
+    #[test]
+    fn dimer_converges_to_the_muller_brown_saddles() {
+        let optimizer = OptimizerConfig::Lbfgs { memory: 5, max_step: 0.01 };
+        for &(saddle, energy) in SADDLES.iter() {
+            let start = saddle + Point { x: 0.03, y: -0.02 };
+            let dimer = Dimer::refine(&MullerBrown, start, Point { x: 1.0, y: 0.0 }, &DimerConfig::default(), optimizer);
+            assert!(dimer.converged, "{:?}", dimer);
+            assert!(dimer.curvature < 0.0);
+            assert!(dimer.center.distance_sq(saddle).sqrt() < 1e-5, "ended at ({}|{})", dimer.center.x, dimer.center.y);
+            assert!((MullerBrown.energy_at(dimer.center) - energy).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::fs;
+use crate::point::Point;
+use crate::image::{Image, PLOT_POINT_SIZE};
+use crate::network::Network;
+
+///at which energies the minima are grouped into superbasins
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DisconnectivityConfig {
+    ///how many evenly spaced levels between the lowest minimum and just above the highest saddle point
+    #[serde(default = "default_levels")]
+    pub(crate) levels: usize,
+    ///the levels themselves, this overrides levels
+    #[serde(default)]
+    pub(crate) thresholds: Option<Vec<f64>>,
+}
+
+fn default_levels() -> usize {
+    20
+}
+
+impl Default for DisconnectivityConfig {
+    fn default() -> Self {
+        DisconnectivityConfig {
+            levels: default_levels(),
+            thresholds: None,
+        }
+    }
+}
+
+///a superbasin at one of the levels, or a single minimum at the bottom of the tree
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Branch {
+    ///the level for a superbasin, the energy of the minimum for a leaf
+    pub(crate) energy: f64,
+    ///all minima that can reach each other below this energy
+    pub(crate) minima: Vec<usize>,
+    pub(crate) children: Vec<Branch>,
+    ///horizontal position in the plot, the leaves are one apart
+    pub(crate) x: f64,
+}
+
+///the disconnectivity graph: which minima can reach each other without crossing a given energy
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Tree {
+    pub(crate) thresholds: Vec<f64>,
+    ///one root for every part of the network that isn't connected to the rest
+    pub(crate) roots: Vec<Branch>,
+    ///the minima from left to right
+    pub(crate) order: Vec<usize>,
+}
+
+impl Tree {
+    pub fn new(network: &Network, config: &DisconnectivityConfig) -> Self {
+        let energies: Vec<f64> = network.nodes.iter().map(|n| n.energy).collect();
+        let mut thresholds = match &config.thresholds {
+            Some(thresholds) => thresholds.clone(),
+            None => even_thresholds(network, config.levels.max(2)),
+        };
+        thresholds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let groups: Vec<Vec<Vec<usize>>> = thresholds.iter().map(|&t| superbasins(network, t)).collect();
+
+        let mut tree = Tree { thresholds, roots: Vec::new(), order: Vec::new() };
+        if let Some(top) = groups.len().checked_sub(1) {
+            let mut roots: Vec<Vec<usize>> = groups[top].clone();
+            roots.sort_by(|a, b| lowest(a, &energies).partial_cmp(&lowest(b, &energies)).unwrap());
+            for members in roots {
+                let root = tree.branch(top, members, &groups, &energies);
+                tree.roots.push(root);
+            }
+        }
+        tree
+    }
+
+    ///the superbasin at level k with everything below it. The leaves are numbered from left to right
+    /// in the order they are reached, so every branch covers a contiguous range
+    fn branch(&mut self, k: usize, members: Vec<usize>, groups: &[Vec<Vec<usize>>], energies: &[f64]) -> Branch {
+        let mut below: Vec<Vec<usize>> = Vec::new();
+        if k > 0 {
+            below.extend(groups[k - 1].iter().filter(|g| members.contains(&g[0])).cloned());
+        }
+        // minima that only show up at this level hang directly from it
+        below.extend(members.iter()
+            .filter(|&&m| k == 0 || energies[m] >= self.thresholds[k - 1])
+            .map(|&m| vec![m]));
+        below.sort_by(|a, b| lowest(a, energies).partial_cmp(&lowest(b, energies)).unwrap());
+
+        let children: Vec<Branch> = below.into_iter().map(|group| {
+            let leaf = group.len() == 1 && (k == 0 || energies[group[0]] >= self.thresholds[k - 1]);
+            if leaf {
+                self.order.push(group[0]);
+                Branch { energy: energies[group[0]], minima: group, children: Vec::new(), x: (self.order.len() - 1) as f64 }
+            } else {
+                self.branch(k - 1, group, groups, energies)
+            }
+        }).collect();
+        let x = children.iter().map(|c| c.x).sum::<f64>() / children.len() as f64;
+        Branch { energy: self.thresholds[k], minima: members, children, x }
+    }
+
+    pub fn save_json(&self, file: &str) -> std::io::Result<()> {
+        fs::write(file, serde_json::ser::to_string_pretty(self).unwrap())
+    }
+
+    ///draws the tree with the energy going up, ticks on the left mark the levels and every minimum
+    /// ends in a red point
+    pub fn paint(&self, file: &str) {
+        let leaves = self.order.len().max(1) as f64;
+        let bottom = self.roots.iter().flat_map(leaf_energies).fold(f64::MAX, f64::min);
+        let top = self.thresholds.iter().cloned().fold(f64::MIN, f64::max);
+        let to_plot = |x: f64, e: f64| Image::plot_point(
+            (x + 0.5) / leaves,
+            if top > bottom { (e - bottom) / (top - bottom) } else { 0.5 },
+        );
+        let img = Image::plot();
+        let mut buffer = img.canvas();
+
+        // energy axis with a tick at every level
+        img.draw_line(&mut buffer, to_plot(-0.5, bottom), to_plot(-0.5, top), &[0, 0, 0]);
+        for &t in &self.thresholds {
+            let tick = to_plot(-0.5, t);
+            img.draw_line(&mut buffer, tick, Point { x: tick.x + 0.02, y: tick.y }, &[0, 0, 0]);
+        }
+
+        let mut stack: Vec<&Branch> = self.roots.iter().collect();
+        while let Some(branch) = stack.pop() {
+            let from = to_plot(branch.x, branch.energy);
+            for child in &branch.children {
+                img.draw_line(&mut buffer, from, to_plot(child.x, child.energy), &[0, 0, 0]);
+                stack.push(child);
+            }
+            if branch.children.is_empty() {
+                img.draw_circle(&mut buffer, from, PLOT_POINT_SIZE, &[255u8, 0, 0]);
+            }
+        }
+
+        buffer.save(file).unwrap();
+    }
+}
+
+///from just above the lowest minimum to one step above the highest saddle point
+fn even_thresholds(network: &Network, levels: usize) -> Vec<f64> {
+    let lowest = network.nodes.iter().map(|n| n.energy).fold(f64::MAX, f64::min);
+    let highest = network.connections.iter().map(|c| c.energy)
+        .chain(network.nodes.iter().map(|n| n.energy))
+        .fold(f64::MIN, f64::max);
+    let step = if highest > lowest { (highest - lowest) / (levels - 1) as f64 } else { 1.0 };
+    (1..=levels).map(|k| lowest + k as f64 * step).collect()
+}
+
+///groups of the minima below the threshold that are connected by saddle points below it
+fn superbasins(network: &Network, threshold: f64) -> Vec<Vec<usize>> {
+    let n = network.nodes.len();
+    // union find, every minimum starts as its own group
+    let mut parent: Vec<usize> = (0..n).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for c in network.connections.iter().filter(|c| c.energy < threshold) {
+        let (a, b) = (root(&mut parent, c.from), root(&mut parent, c.to));
+        parent[a] = b;
+    }
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_root: Vec<Option<usize>> = vec![None; n];
+    for i in (0..n).filter(|&i| network.nodes[i].energy < threshold) {
+        let r = root(&mut parent, i);
+        match group_of_root[r] {
+            Some(g) => groups[g].push(i),
+            None => {
+                group_of_root[r] = Some(groups.len());
+                groups.push(vec![i]);
+            }
+        }
+    }
+    groups
+}
+
+fn lowest(group: &[usize], energies: &[f64]) -> f64 {
+    group.iter().map(|&m| energies[m]).fold(f64::MAX, f64::min)
+}
+
+fn leaf_energies(branch: &Branch) -> Vec<f64> {
+    if branch.children.is_empty() {
+        vec![branch.energy]
+    } else {
+        branch.children.iter().flat_map(leaf_energies).collect()
+    }
+}
@@ -0,0 +1,126 @@
+use crate::point::Point;
+use crate::pes::{PotentialEnergySurface, Hessian};
+
+///the result of comparing the analytic gradient with central finite differences
+#[derive(Debug, Copy, Clone)]
+pub struct GradientCheck {
+    pub(crate) points_checked: usize,
+    pub(crate) max_deviation: f64,
+    pub(crate) worst_point: Point,
+    pub(crate) analytic: Point,
+    pub(crate) numeric: Point,
+}
+
+///the negative gradient from central differences with step size h,
+/// so it can be compared directly to PotentialEnergySurface::gradient_at
+pub fn numerical_gradient(pes: &dyn PotentialEnergySurface, p: Point, h: f64) -> Point {
+    let dx = Point { x: h, y: 0.0 };
+    let dy = Point { x: 0.0, y: h };
+    Point {
+        x: -(pes.energy_at(p + dx) - pes.energy_at(p - dx)) / (2.0 * h),
+        y: -(pes.energy_at(p + dy) - pes.energy_at(p - dy)) / (2.0 * h),
+    }
+}
+
+///the hessian from central differences of the analytic gradient with step size h
+pub fn numerical_hessian(pes: &dyn PotentialEnergySurface, p: Point, h: f64) -> Hessian {
+    let dx = Point { x: h, y: 0.0 };
+    let dy = Point { x: 0.0, y: h };
+    // gradient_at is the negative gradient
+    let ddx = (0.5 / h) * (pes.gradient_at(p - dx) - pes.gradient_at(p + dx));
+    let ddy = (0.5 / h) * (pes.gradient_at(p - dy) - pes.gradient_at(p + dy));
+    Hessian { xx: ddx.x, xy: 0.5 * (ddx.y + ddy.x), yy: ddy.y }
+}
+
+///the largest deviation between hessian_at and the numerical hessian over all given points,
+/// None if the surface has no analytic hessian
+pub fn check_hessian(pes: &dyn PotentialEnergySurface, points: &[Point], h: f64) -> Option<(f64, Point)> {
+    let mut worst = (0.0, Point { x: 0.0, y: 0.0 });
+    for &p in points {
+        let analytic = pes.hessian_at(p)?;
+        let numeric = numerical_hessian(pes, p, h);
+        let deviation = (analytic.xx - numeric.xx).abs()
+            .max((analytic.xy - numeric.xy).abs())
+            .max((analytic.yy - numeric.yy).abs());
+        if deviation.is_nan() || deviation > worst.0 {
+            worst = (deviation, p);
+        }
+    }
+    Some(worst)
+}
+
+///compares gradient_at with the numerical gradient at all given points and keeps the worst one
+pub fn check_gradient(pes: &dyn PotentialEnergySurface, points: &[Point], h: f64) -> GradientCheck {
+    let mut check = GradientCheck {
+        points_checked: 0,
+        max_deviation: 0.0,
+        worst_point: Point { x: 0.0, y: 0.0 },
+        analytic: Point { x: 0.0, y: 0.0 },
+        numeric: Point { x: 0.0, y: 0.0 },
+    };
+    for &p in points {
+        let analytic = pes.gradient_at(p);
+        let numeric = numerical_gradient(pes, p, h);
+        let deviation = analytic.distance_sq(numeric).sqrt();
+        check.points_checked += 1;
+        // a NaN deviation is reported as well
+        if deviation.is_nan() || deviation > check.max_deviation {
+            check.max_deviation = deviation;
+            check.worst_point = p;
+            check.analytic = analytic;
+            check.numeric = numeric;
+        }
+    }
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pes::{Gaussian, Model, PES};
+    use crate::muller_brown::MullerBrown;
+
+    ///an n by n grid over the rectangle from (x0|y0) with the given size
+    fn grid(x0: f64, y0: f64, width: f64, height: f64, n: usize) -> Vec<Point> {
+        (0..n * n).map(|k| Point {
+            x: x0 + width * (k / n) as f64 / (n - 1) as f64,
+            y: y0 + height * (k % n) as f64 / (n - 1) as f64,
+        }).collect()
+    }
+
+    fn gaussians(angle: f64) -> PES {
+        PES {
+            scale: 1.0,
+            model: Model::Gaussians,
+            gaussians: vec![
+                Gaussian { a: -7.0, x0: 15.0, sig_x: 5.0, y0: 7.5, sig_y: 3.0, angle },
+                Gaussian { a: -13.0, x0: 23.0, sig_x: 2.0, y0: 5.0, sig_y: 4.0, angle: -angle },
+                Gaussian { a: 10.0, x0: 10.0, sig_x: 3.0, y0: 15.0, sig_y: 1.5, angle: 2.0 * angle },
+            ],
+        }
+    }
+
+    fn assert_derivatives_match(pes: &dyn PotentialEnergySurface, points: &[Point], h: f64, tolerance: f64) {
+        let check = check_gradient(pes, points, h);
+        assert_eq!(check.points_checked, points.len());
+        assert!(check.max_deviation < tolerance, "gradient deviates by {:e} at ({}|{})",
+                check.max_deviation, check.worst_point.x, check.worst_point.y);
+        let (deviation, worst) = check_hessian(pes, points, h).expect("the surface has a hessian");
+        assert!(deviation < tolerance, "hessian deviates by {:e} at ({}|{})", deviation, worst.x, worst.y);
+    }
+
+    #[test]
+    fn axis_aligned_gaussians() {
+        assert_derivatives_match(&gaussians(0.0), &grid(0.0, 0.0, 27.0, 25.0, 30), 1e-5, 1e-6);
+    }
+
+    #[test]
+    fn rotated_gaussians() {
+        assert_derivatives_match(&gaussians(35.0), &grid(0.0, 0.0, 27.0, 25.0, 30), 1e-5, 1e-6);
+    }
+
+    #[test]
+    fn muller_brown() {
+        assert_derivatives_match(&MullerBrown, &grid(-1.5, -0.5, 2.7, 2.5, 30), 1e-6, 1e-3);
+    }
+}
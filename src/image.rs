@@ -1,4 +1,4 @@
-use crate::pes::PES;
+use crate::pes::PotentialEnergySurface;
 use crate::chain::Chain;
 use crate::point::Point;
 use self::image::{ImageBuffer, Rgb};
@@ -22,6 +22,11 @@ pub struct ImageConfig {
     pub(crate) line_width: f64,
 }
 
+///how much wider than high the plots made with Image::plot are, so the points end up round
+const PLOT_ASPECT: f64 = 1.5;
+///the size of the points drawn into plots made with Image::plot
+pub const PLOT_POINT_SIZE: f64 = 0.008;
+
 #[derive(Debug)]
 pub struct Image {
     config: ImageConfig,
@@ -29,7 +34,7 @@ pub struct Image {
 }
 
 impl Image {
-    pub fn new(image_config: ImageConfig, pes: &PES) -> Self {
+    pub fn new(image_config: ImageConfig, pes: &dyn PotentialEnergySurface) -> Self {
         let mut img = Image {
             config: image_config,
             image: image::ImageBuffer::new(image_config.resolution_x as u32, image_config.resolution_y as u32),
@@ -38,8 +43,46 @@ impl Image {
         img
     }
 
+    ///an empty white canvas with the mapping of the config, for plots that don't show the PES
+    pub fn blank(image_config: ImageConfig) -> Self {
+        Image {
+            config: image_config,
+            image: image::ImageBuffer::from_pixel(
+                image_config.resolution_x as u32,
+                image_config.resolution_y as u32,
+                image::Rgb([255u8, 255, 255]),
+            ),
+        }
+    }
+
+    ///an empty 1200x800 canvas for plots like the energy profile. Everything placed with plot_point
+    /// between 0 and 1 in both directions is visible, with a margin around it
+    pub fn plot() -> Self {
+        Image::blank(ImageConfig {
+            contour_lines: 1.0,
+            x0: -0.05 * PLOT_ASPECT,
+            y0: -0.1,
+            width: 1.1 * PLOT_ASPECT,
+            height: 1.2,
+            resolution_x: 1200,
+            resolution_y: 800,
+            point_size: PLOT_POINT_SIZE,
+            line_width: 0.001,
+        })
+    }
+
+    ///where a point of a plot made with Image::plot goes, given as fractions of the plotted area
+    pub fn plot_point(x: f64, y: f64) -> Point {
+        Point { x: PLOT_ASPECT * x, y }
+    }
+
+    ///a copy of the background to draw on
+    pub fn canvas(&self) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        self.image.clone()
+    }
+
     ///draw the PES so we don't need to query the PES for every single pixel in every loop
-    fn initialize_pes_image(&mut self, pes: &PES) {
+    fn initialize_pes_image(&mut self, pes: &dyn PotentialEnergySurface) {
         let config = self.config;
         let mut min = f64::MAX;
         let mut max = f64::MIN;
@@ -62,7 +105,7 @@ impl Image {
         }
     }
 
-    fn draw_line(&self, image_buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, start: Point, end: Point, color: &[u8; 3]) {
+    pub(crate) fn draw_line(&self, image_buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, start: Point, end: Point, color: &[u8; 3]) {
         //https://en.wikipedia.org/wiki/Digital_differential_analyzer_(graphics_algorithm)
         let (img_start_x, img_start_y) = self.config.pixel_for_point(start);
         let (img_end_x, img_end_y) = self.config.pixel_for_point(end);
@@ -88,7 +131,7 @@ impl Image {
         }
     }
 
-    fn draw_circle(&self, image_buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, p: Point, radius: f64, color: &[u8; 3]) {
+    pub(crate) fn draw_circle(&self, image_buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, p: Point, radius: f64, color: &[u8; 3]) {
         let (x, y) = self.config.pixel_for_point(p);
         let rx = (radius * self.config.resolution_x as f64 / self.config.width) as i32;
         let ry = (radius * self.config.resolution_y as f64 / self.config.height) as i32;
@@ -110,21 +153,30 @@ impl Image {
         }
     }
 
-    fn draw_gradients(&self, image_buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, points: &Chain, pes: &PES) {
+    fn draw_gradients(&self, image_buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, points: &Chain, pes: &dyn PotentialEnergySurface) {
         for point in &points.elements {
             self.draw_line(image_buffer, *point, *point + pes.gradient_at(*point), &[0, 0, 255u8]);
         }
     }
 
     fn draw_connections(&self, image_buffer: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, points: &Chain) {
-        for i in 0..points.config.elements {
+        for i in 0..points.elements.len() - 1 {
+            // a growing string has a gap between its two halves
+            if points.growing == Some(i + 1) {
+                continue;
+            }
             let a = *points.elements.get(i).unwrap();
             let b = *points.elements.get(i + 1).unwrap();
             self.draw_line(image_buffer, a, b, &[0, 255u8, 0])
         }
     }
 
-    pub fn paint(&self, filename: &str, points: &Chain, pes: &PES) {
+    pub fn paint(&self, filename: &str, points: &Chain, pes: &dyn PotentialEnergySurface) {
+        self.paint_with_path(filename, points, pes, &[]);
+    }
+
+    ///like paint, with another path like the reaction coordinate drawn in orange on top
+    pub fn paint_with_path(&self, filename: &str, points: &Chain, pes: &dyn PotentialEnergySurface, path: &[Point]) {
         let mut image_buffer = self.image.clone();
 
         //add the points along our chain
@@ -136,6 +188,10 @@ impl Image {
         //add the connections between the points
         self.draw_connections(&mut image_buffer, points);
 
+        for pair in path.windows(2) {
+            self.draw_line(&mut image_buffer, pair[0], pair[1], &[255u8, 128, 0]);
+        }
+
         image_buffer.save(filename).unwrap();
     }
 }
@@ -155,6 +211,25 @@ impl ImageConfig {
         (px as i32, self.resolution_y - 1 - py as i32)
     }
 
+    ///if the point lies within the depicted area
+    pub fn contains(&self, p: Point) -> bool {
+        p.x >= self.x0 && p.x <= self.x0 + self.width && p.y >= self.y0 && p.y <= self.y0 + self.height
+    }
+
+    ///an evenly spaced grid of nx times ny points covering the depicted area
+    pub fn grid(&self, nx: usize, ny: usize) -> Vec<Point> {
+        let mut points = Vec::with_capacity(nx * ny);
+        for i in 0..nx {
+            for j in 0..ny {
+                points.push(Point {
+                    x: self.x0 + self.width * (i as f64 + 0.5) / nx as f64,
+                    y: self.y0 + self.height * (j as f64 + 0.5) / ny as f64,
+                });
+            }
+        }
+        points
+    }
+
     fn pixel_in_image(&self, x: i32, y: i32) -> bool {
         x >= 0 && x < self.resolution_x && y >= 0 && y < self.resolution_y
     }
@@ -0,0 +1,221 @@
+use std::fs;
+use crate::point::Point;
+use crate::chain::arc_lengths;
+use crate::pes::PotentialEnergySurface;
+
+///how each step down the intrinsic reaction coordinate is taken
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    ///a straight step along the force
+    Euler,
+    ///fourth order Runge-Kutta on the direction of the force
+    #[default]
+    Rk4,
+    ///Gonzalez-Schlegel: a half step to a pivot point, then the lowest point on the circle around it
+    GonzalezSchlegel,
+}
+
+///how the path of steepest descent from the saddle point down to both minima is followed
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct IrcConfig {
+    ///where to start, the refined or highest point of the path is used if left out
+    #[serde(default)]
+    pub(crate) saddle: Option<Point>,
+    #[serde(default)]
+    pub(crate) integrator: Integrator,
+    ///length of each step along the path
+    #[serde(default = "default_step_size")]
+    pub(crate) step_size: f64,
+    ///how far to move off the saddle point along the negative curvature, one step if left out
+    #[serde(default)]
+    pub(crate) displacement: Option<f64>,
+    ///a branch ends once the force is smaller than this
+    #[serde(default = "default_min_force")]
+    pub(crate) min_force: f64,
+    ///or after this many steps
+    #[serde(default = "default_max_steps")]
+    pub(crate) max_steps: usize,
+}
+
+fn default_step_size() -> f64 {
+    1e-2
+}
+
+fn default_min_force() -> f64 {
+    1e-4
+}
+
+fn default_max_steps() -> usize {
+    10000
+}
+
+impl Default for IrcConfig {
+    fn default() -> Self {
+        IrcConfig {
+            saddle: None,
+            integrator: Integrator::default(),
+            step_size: default_step_size(),
+            displacement: None,
+            min_force: default_min_force(),
+            max_steps: default_max_steps(),
+        }
+    }
+}
+
+///one half of the reaction path, from next to the saddle point down to a minimum
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Branch {
+    pub(crate) points: Vec<Point>,
+    ///if the branch ended in a minimum instead of running out of steps
+    pub(crate) converged: bool,
+}
+
+///the intrinsic reaction coordinate through a saddle point
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Irc {
+    pub(crate) saddle: Point,
+    ///the branch that goes against the direction it was started with
+    pub(crate) backward: Branch,
+    pub(crate) forward: Branch,
+}
+
+impl Irc {
+    ///follows the steepest descent from the saddle in both directions. Direction should be the mode
+    /// of negative curvature, the forward branch starts along it
+    pub fn trace(pes: &dyn PotentialEnergySurface, saddle: Point, direction: Point, config: &IrcConfig) -> Self {
+        let displacement = config.displacement.unwrap_or(config.step_size);
+        let direction = direction.normed();
+        Irc {
+            saddle,
+            backward: descend(pes, saddle + (-displacement) * direction, config),
+            forward: descend(pes, saddle + displacement * direction, config),
+        }
+    }
+
+    ///the whole path from the end of the backward branch over the saddle to the end of the forward one
+    pub fn path(&self) -> Vec<Point> {
+        let mut path: Vec<Point> = self.backward.points.iter().rev().copied().collect();
+        path.push(self.saddle);
+        path.extend_from_slice(&self.forward.points);
+        path
+    }
+
+    pub fn save_csv(&self, file: &str, pes: &dyn PotentialEnergySurface) -> std::io::Result<()> {
+        let path = self.path();
+        let lengths = arc_lengths(&path);
+        // the saddle point sits at zero
+        let offset = lengths[self.backward.points.len()];
+        let mut data = String::from("index,arc_length,x,y,energy\n");
+        for (i, p) in path.iter().enumerate() {
+            data += &format!("{},{},{},{},{}\n", i, lengths[i] - offset, p.x, p.y, pes.energy_at(*p));
+        }
+        fs::write(file, data)
+    }
+
+    pub fn save_json(&self, file: &str) -> std::io::Result<()> {
+        fs::write(file, serde_json::ser::to_string_pretty(self).unwrap())
+    }
+}
+
+///steps downhill from start until the force vanishes or the energy stops decreasing
+fn descend(pes: &dyn PotentialEnergySurface, start: Point, config: &IrcConfig) -> Branch {
+    let mut points = vec![start];
+    let mut current = start;
+    let mut energy = pes.energy_at(current);
+    for _ in 0..config.max_steps {
+        if pes.gradient_at(current).norm() < config.min_force {
+            return Branch { points, converged: true };
+        }
+        let next = step(pes, current, config.step_size, config.integrator);
+        let next_energy = pes.energy_at(next);
+        // stepped over the minimum, it is closer than one step
+        if next_energy.is_nan() || next_energy >= energy {
+            return Branch { points, converged: true };
+        }
+        points.push(next);
+        current = next;
+        energy = next_energy;
+    }
+    Branch { points, converged: false }
+}
+
+///the normed force, the direction of steepest descent
+fn downhill(pes: &dyn PotentialEnergySurface, p: Point) -> Point {
+    pes.gradient_at(p).normed()
+}
+
+fn step(pes: &dyn PotentialEnergySurface, p: Point, h: f64, integrator: Integrator) -> Point {
+    match integrator {
+        Integrator::Euler => p + h * downhill(pes, p),
+        Integrator::Rk4 => {
+            let k1 = downhill(pes, p);
+            let k2 = downhill(pes, p + (0.5 * h) * k1);
+            let k3 = downhill(pes, p + (0.5 * h) * k2);
+            let k4 = downhill(pes, p + h * k3);
+            p + (h / 6.0) * (k1 + 2.0 * k2 + 2.0 * k3 + k4)
+        }
+        Integrator::GonzalezSchlegel => gonzalez_schlegel(pes, p, h),
+    }
+}
+
+///half a step to the pivot, then the point with the lowest energy on the circle of half a step around
+/// the pivot. There the force points straight at the pivot, found with the secant method on the angle
+fn gonzalez_schlegel(pes: &dyn PotentialEnergySurface, p: Point, h: f64) -> Point {
+    let radius = 0.5 * h;
+    let pivot = p + radius * downhill(pes, p);
+    let on_circle = |angle: f64| pivot + radius * Point { x: angle.cos(), y: angle.sin() };
+    // the force along the circle, zero where the energy on the circle is lowest
+    let sideways = |angle: f64| pes.gradient_at(on_circle(angle)).dot_product(Point { x: -angle.sin(), y: angle.cos() });
+
+    let guess = downhill(pes, pivot);
+    let start = guess.y.atan2(guess.x);
+    let mut a = start;
+    let mut b = a + 1e-3;
+    let (mut fa, mut fb) = (sideways(a), sideways(b));
+    for _ in 0..20 {
+        if fb == fa || fb.abs() < 1e-12 {
+            break;
+        }
+        let next = b - fb * (b - a) / (fb - fa);
+        a = b;
+        fa = fb;
+        b = next;
+        fb = sideways(b);
+    }
+    // the secant method wandered off, fall back to the direction of the guess
+    if b.is_nan() || (b - start).cos() < 0.0 {
+        return pivot + radius * guess;
+    }
+    on_circle(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muller_brown::{MullerBrown, MINIMA, SADDLES};
+
+    #[test]
+    fn irc_ends_in_the_muller_brown_minima() {
+        // the first saddle connects the deepest minimum with the shallow one, the second one the
+        // shallow minimum with the remaining one
+        let connections = [
+            (SADDLES[0].0, [MINIMA[0].0, MINIMA[2].0]),
+            (SADDLES[1].0, [MINIMA[2].0, MINIMA[1].0]),
+        ];
+        for &integrator in [Integrator::Euler, Integrator::Rk4, Integrator::GonzalezSchlegel].iter() {
+            let config = IrcConfig { integrator, step_size: 1e-3, ..IrcConfig::default() };
+            for &(saddle, minima) in connections.iter() {
+                // along the mode of negative curvature
+                let (_, eigenvectors) = MullerBrown.hessian_at(saddle).unwrap().eigen();
+                let irc = Irc::trace(&MullerBrown, saddle, eigenvectors[0], &config);
+
+                assert!(irc.backward.converged && irc.forward.converged, "{:?}", integrator);
+                let ends = [irc.backward.points.last().unwrap(), irc.forward.points.last().unwrap()];
+                // the branches stop within a step of the minimum
+                let reached = |minimum: Point| ends.iter().any(|end| end.distance_sq(minimum).sqrt() < config.step_size);
+                assert!(reached(minima[0]) && reached(minima[1]), "{:?} ended at {:?}", integrator, ends);
+            }
+        }
+    }
+}
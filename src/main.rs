@@ -1,10 +1,25 @@
 #[macro_use]
 extern crate serde_derive;
 
-use crate::pes::PES;
+use crate::pes::{PES, Model, PotentialEnergySurface};
 use crate::pes::Gaussian;
+use crate::muller_brown::{MullerBrown, MINIMA, SADDLES};
 use crate::image::{Image, ImageConfig};
-use crate::chain::{Chain, ChainConfig};
+use crate::chain::{Chain, ChainConfig, Method};
+use crate::optimizer::OptimizerConfig;
+use crate::convergence::ConvergenceConfig;
+use crate::stability::StabilityConfig;
+use crate::checkpoint::{Checkpoint, CheckpointConfig};
+use crate::profile::Profile;
+use crate::stationary::Characterization;
+use crate::dimer::{Dimer, DimerConfig};
+use crate::irc::{Irc, IrcConfig};
+use crate::rates::{Reaction, RateConfig};
+use crate::minima::{Minima, MinimaConfig};
+use crate::network::{Network, NetworkConfig};
+use crate::relaxation::{Relaxation, LoopState, Event};
+use crate::disconnectivity::{Tree, DisconnectivityConfig};
+use crate::minimizer::MinimizerConfig;
 use crate::point::Point;
 use std::time::SystemTime;
 use std::{fs, env};
@@ -14,32 +29,114 @@ mod point;
 mod pes;
 mod image;
 mod chain;
+mod muller_brown;
+mod gradient_check;
+mod optimizer;
+mod convergence;
+mod stability;
+mod checkpoint;
+mod profile;
+mod stationary;
+mod dimer;
+mod irc;
+mod rates;
+mod minima;
+mod network;
+mod disconnectivity;
+mod minimizer;
+mod relaxation;
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Config {
     convergence_limit: f64,
+    ///points with a larger gradient norm are not classified as stationary points
+    #[serde(default = "default_stationary_gradient")]
+    stationary_gradient: f64,
+    #[serde(default)]
+    convergence: ConvergenceConfig,
+    #[serde(default)]
+    stability: StabilityConfig,
+    #[serde(default)]
+    checkpoint: CheckpointConfig,
+    ///refine the saddle point with the dimer method after the simulation
+    #[serde(default)]
+    dimer: Option<DimerConfig>,
+    ///follow the intrinsic reaction coordinate down from the saddle point after the simulation
+    #[serde(default)]
+    irc: Option<IrcConfig>,
+    ///estimate rate constants with harmonic transition state theory after the simulation
+    #[serde(default)]
+    rates: Option<RateConfig>,
+    ///how --find-minima searches the surface
+    #[serde(default)]
+    minima: MinimaConfig,
+    ///how --network connects the minima
+    #[serde(default)]
+    network: NetworkConfig,
+    ///the levels of the disconnectivity graph drawn by --network
+    #[serde(default)]
+    disconnectivity: DisconnectivityConfig,
     pes: PES,
     path: ChainConfig,
     image: ImageConfig,
 }
 
+///what the program was asked to do on the command line
+enum Mode {
+    ///simulate, optionally continuing from the given checkpoint file
+    Simulate(Option<String>),
+    CheckGradient,
+    FindMinima,
+    Network,
+}
+
 fn main() {
     // argument parsing first, in case we need to catch a --help and exit early
-    arg_parse();
+    match arg_parse() {
+        Mode::Simulate(resume) => simulate(resume),
+        Mode::CheckGradient => check_gradient(),
+        Mode::FindMinima => find_minima(),
+        Mode::Network => network(),
+    }
+}
 
+fn simulate(resume: Option<String>) {
     // keep track of how long everything takes
     let mut start_time = SystemTime::now();
 
     // read the config
     let config = load_config();
+    println!("Simulating...");
     ensure_image_directory();
 
+    let config_hash = simulation_hash(&config);
+
     //create mep
     let pes = config.pes;
-    //create chain
-    let mut chain_config = config.path;
-    chain_config.relax_ends(&pes, config.convergence_limit);
-    let mut chain = Chain::new(chain_config);
+    //create chain, or pick it up where the checkpoint left it
+    let (mut chain, mut state, domain) = match resume {
+        Some(file) => resume_checkpoint(&file, config_hash),
+        None => {
+            let mut chain_config = config.path;
+            if let Err(err) = chain_config.load_path_file() {
+                println!("Could not read the path file {:?}!", chain_config.path_file);
+                println!("Error: {}", err);
+                exit(6);
+            }
+            if let Some(relaxed) = chain_config.relax_ends(&pes, config.convergence_limit) {
+                for (name, result) in ["start", "end"].iter().zip(relaxed.iter()) {
+                    if result.converged() {
+                        println!("relaxed the {}: {}", name, result);
+                    } else {
+                        println!("Could not relax the {} into its minimum: {}", name, result);
+                    }
+                }
+            }
+            let chain = Chain::new(chain_config);
+            let state = LoopState::new(config.stability.allowed_energy_rise(&chain, &pes));
+            (chain, state, config.image)
+        }
+    };
 
     //set up our image generator
     let img = Image::new(config.image, &pes);
@@ -48,29 +145,427 @@ fn main() {
     print_elapsed_time(&mut start_time);
 
     // iterate until we reached a stable state
-    let mut counter = 0;
-    let mut last_energy;
-    let mut energy = chain.energy(&pes);
-    println!("starting with initial energy: {}", energy);
-    loop {
-        // save the state
-        img.paint(&*format!("images/progress_{:04}.png", counter), &chain, &pes);
-
-        //move to a better position
-        chain.iterate(&pes);
-
-        // increment counter and update energy values
-        counter += 1;
-        last_energy = energy;
-        energy = chain.energy(&pes);
-
-        // print info
-        print!("iteration: {:4} resulted in average energy: {:15.10} and took: ", counter, energy);
+    let relaxation = Relaxation {
+        convergence: &config.convergence,
+        convergence_limit: config.convergence_limit,
+        stability: &config.stability,
+        domain: &domain,
+    };
+    let checkpoint_config = &config.checkpoint;
+    println!("starting with initial energy: {}", chain.energy(&pes));
+    let mut on_event = |event: Event| match event {
+        // save the state, unless we are retrying a state that was already saved
+        Event::Before(chain) => img.paint(&format!("images/progress_{:04}.png", chain.iteration), chain, &pes),
+        Event::Retry { iteration, problem } => {
+            println!("iteration: {:4} was unstable: {}", iteration, problem);
+            println!("Going back to the last stable state and reducing the step by a factor of {}.",
+                     relaxation.stability.step_reduction);
+        }
+        Event::Iterated { chain, energy, progress, state } => {
+            print!("iteration: {:4} resulted in average energy: {:15.10} max force: {:12.6e} and took: ",
+                   chain.iteration, energy, progress.max_force());
+            print_elapsed_time(&mut start_time);
+            if let Some(interval) = checkpoint_config.interval {
+                if chain.iteration.is_multiple_of(interval) {
+                    save_checkpoint(&checkpoint_config.file, config_hash, &domain, state, chain);
+                }
+            }
+        }
+    };
+    let reason = match relaxation.run(&mut chain, &pes, &mut state, Some(&mut on_event)) {
+        Ok(reason) => reason,
+        Err(problem) => {
+            println!("iteration: {:4} was unstable: {}", chain.iteration + 1, problem);
+            println!("The simulation stays unstable after reducing the step {} times in a row, giving up!",
+                     config.stability.max_retries);
+            println!("Try a smaller step for the optimizer, fewer elements or a smaller spring_constant.");
+            exit(4);
+        }
+    };
+    println!("stopped after {} iterations, because {}", chain.iteration, reason);
+    if config.checkpoint.interval.is_some() {
+        save_checkpoint(&config.checkpoint.file, config_hash, &domain, &state, &chain);
+    }
+
+    if let Some(i) = chain.climbing_image(&pes) {
+        let saddle = chain.elements[i];
+        println!("saddle point (climbing image {}): ({:15.10}|{:15.10}) with energy: {:15.10}",
+                 i, saddle.x, saddle.y, pes.energy_at(saddle));
+    }
+
+    save_profile(&chain, &pes);
+    let stationary_gradient = config.stationary_gradient;
+    print_stationary_points(&chain, &pes, stationary_gradient);
+    let dimer = config.dimer.map(|dimer_config| refine_saddle(&chain, &pes, &dimer_config, stationary_gradient));
+    let irc = config.irc.map(|irc_config| trace_irc(&chain, &pes, &irc_config, dimer.as_ref(), &img));
+    if let Some(rate_config) = &config.rates {
+        print_rates(&chain, &pes, rate_config, dimer.as_ref(), irc.as_ref(), config.convergence_limit, stationary_gradient);
+    }
+
+    if pes.model == Model::MullerBrown {
+        print_muller_brown_comparison(&chain, &pes);
+    }
+}
+
+///writes the energy profile along the final path as csv, json and as a plot
+fn save_profile(chain: &Chain, pes: &PES) {
+    let profile = Profile::new(chain, pes);
+    let top = profile.points[profile.highest];
+    println!("highest point along the path: element {} at ({:15.10}|{:15.10}) with energy: {:15.10}",
+             profile.highest, top.position.x, top.position.y, top.energy);
+    println!("barrier: {:15.10} forward, {:15.10} reverse", profile.forward_barrier, profile.reverse_barrier);
+    let maximum = profile.interpolated_maximum;
+    let first = profile.points[0].energy;
+    let last = profile.points[profile.points.len() - 1].energy;
+    println!("interpolated maximum at ({:15.10}|{:15.10}) with energy: {:15.10}",
+             maximum.position.x, maximum.position.y, maximum.energy);
+    println!("interpolated barrier: {:15.10} forward, {:15.10} reverse", maximum.energy - first, maximum.energy - last);
+
+    let results = [
+        profile.save_csv("images/profile.csv"),
+        profile.save_interpolated_csv("images/profile_interpolated.csv"),
+        profile.save_json("images/profile.json"),
+    ];
+    for result in results.iter() {
+        if let Err(err) = result {
+            println!("Could not save the energy profile!");
+            println!("Error: {}", err);
+        }
+    }
+    profile.paint("images/profile.png");
+}
+
+///classify the ends and the highest image of the final chain by the eigenvalues of the hessian
+fn print_stationary_points(chain: &Chain, pes: &PES, stationary_gradient: f64) {
+    let last = chain.elements.len() - 1;
+    let highest = chain.saddle_image(pes);
+    let points = [("start", 0), ("highest image", highest), ("end", last)];
+    for (name, i) in points.iter() {
+        match Characterization::at(pes, chain.elements[*i], stationary_gradient) {
+            Some(characterization) => print!("{} (element {}): {}", name, i, characterization),
+            None => println!("{} (element {}): the PES has no hessian to classify it", name, i),
+        }
+    }
+}
+
+///prints the eigenanalysis of the hessian at p, if the surface has one
+fn print_characterization(pes: &PES, p: Point, stationary_gradient: f64) {
+    match Characterization::at(pes, p, stationary_gradient) {
+        Some(characterization) => print!("{}", characterization),
+        None => println!("the PES has no hessian to classify ({}|{})", p.x, p.y),
+    }
+}
+
+///run the dimer method from the highest image of the final chain, along the tangent of the path
+fn refine_saddle(chain: &Chain, pes: &PES, config: &DimerConfig, stationary_gradient: f64) -> Dimer {
+    let i = chain.saddle_image(pes);
+    let tangent = chain.tangents(pes)[i];
+    let dimer = Dimer::refine(pes, chain.elements[i], tangent, config, chain.optimizer.config);
+    let p = dimer.center;
+    if dimer.converged {
+        println!("dimer converged after {} iterations", dimer.iterations);
+    } else {
+        println!("dimer did not converge within {} iterations, remaining force: {:e}", dimer.iterations, dimer.force);
+    }
+    println!("refined saddle point: ({:15.10}|{:15.10}) with energy: {:15.10} and curvature: {:e} along ({:10.6}|{:10.6})",
+             p.x, p.y, pes.energy_at(p), dimer.curvature, dimer.orientation.x, dimer.orientation.y);
+    println!("       moved by {:e} from element {}", (p - chain.elements[i]).norm(), i);
+    if let Some(characterization) = Characterization::at(pes, p, stationary_gradient) {
+        print!("refined saddle point: {}", characterization);
+    }
+    dimer
+}
+
+///follow the steepest descent from the saddle point to both sides, save it and draw it over the
+/// final path. The forward branch is the one heading towards the end of the path
+fn trace_irc(chain: &Chain, pes: &PES, config: &IrcConfig, dimer: Option<&Dimer>, img: &Image) -> Irc {
+    let i = chain.saddle_image(pes);
+    let saddle = config.saddle
+        .or_else(|| dimer.map(|d| d.center))
+        .unwrap_or(chain.elements[i]);
+    let mut direction = pes.hessian_at(saddle).map(|h| h.eigen().1[0])
+        .or_else(|| dimer.map(|d| d.orientation))
+        .unwrap_or_else(|| chain.tangents(pes)[i]);
+    if direction.dot_product(chain.elements[chain.elements.len() - 1] - chain.elements[0]) < 0.0 {
+        direction = -1.0 * direction;
+    }
+    let irc = Irc::trace(pes, saddle, direction, config);
+
+    println!("intrinsic reaction coordinate from ({:15.10}|{:15.10}) with energy: {:15.10}",
+             saddle.x, saddle.y, pes.energy_at(saddle));
+    for (name, branch) in [("backward", &irc.backward), ("forward", &irc.forward)].iter() {
+        let p = branch.points[branch.points.len() - 1];
+        println!("{:>9}: {} steps to ({:15.10}|{:15.10}) with energy: {:15.10}{}",
+                 name, branch.points.len(), p.x, p.y, pes.energy_at(p),
+                 if branch.converged { "" } else { " (ran out of steps)" });
+    }
+
+    let results = [
+        irc.save_csv("images/irc.csv", pes),
+        irc.save_json("images/irc.json"),
+    ];
+    for result in results.iter() {
+        if let Err(err) = result {
+            println!("Could not save the reaction coordinate!");
+            println!("Error: {}", err);
+        }
+    }
+    img.paint_with_path("images/irc.png", chain, pes, &irc.path());
+    irc
+}
+
+///harmonic transition state theory over the saddle point in both directions, printed and saved as an
+/// Arrhenius table. The minima are the ends of the IRC if it was traced, otherwise the ends of the path
+/// as long as it doesn't pass through another minimum on the way to the saddle point
+fn print_rates(chain: &Chain, pes: &PES, config: &RateConfig, dimer: Option<&Dimer>, irc: Option<&Irc>,
+               convergence_limit: f64, stationary_gradient: f64) {
+    let s = chain.saddle_image(pes);
+    let last = chain.elements.len() - 1;
+    let energies: Vec<f64> = chain.elements.iter().map(|&p| pes.energy_at(p)).collect();
+    // the element where the path first dips on its way from the end to the saddle point
+    let dip = |range: std::ops::Range<usize>| range.into_iter().find(|&k| energies[k] < energies[k - 1] && energies[k] < energies[k + 1]);
+    let minima: [(&str, Result<Point, String>); 2] = match irc {
+        Some(irc) => [
+            ("forward", irc_end(&irc.backward, chain, pes, convergence_limit)),
+            ("reverse", irc_end(&irc.forward, chain, pes, convergence_limit)),
+        ],
+        None => [
+            ("forward", match dip(1..s) {
+                Some(k) => Err(format!("the path passes through a minimum at element {}, trace the irc to find the right one", k)),
+                None => Ok(chain.elements[0]),
+            }),
+            ("reverse", match dip(s + 1..last) {
+                Some(k) => Err(format!("the path passes through a minimum at element {}, trace the irc to find the right one", k)),
+                None => Ok(chain.elements[last]),
+            }),
+        ],
+    };
+    let saddle = irc.map(|irc| irc.saddle)
+        .or_else(|| dimer.map(|d| d.center))
+        .unwrap_or(chain.elements[s]);
+    let mut reactions = Vec::new();
+    for (name, minimum) in minima.iter() {
+        let reaction = match minimum.clone().and_then(|minimum| Reaction::new(pes, minimum, saddle, config, stationary_gradient)) {
+            Ok(reaction) => reaction,
+            Err(err) => {
+                println!("Could not compute the {} rate: {}", name, err);
+                continue;
+            }
+        };
+        println!("{} reaction over a barrier of {:15.10}", name, reaction.barrier);
+        println!("    frequencies in the minimum: {:.2} and {:.2} cm^-1, at the saddle point: {:.2} and {:.2}i cm^-1",
+                 rates::wavenumber(reaction.minimum_frequencies[0]), rates::wavenumber(reaction.minimum_frequencies[1]),
+                 rates::wavenumber(reaction.saddle_frequency), rates::wavenumber(reaction.imaginary_frequency));
+        println!("    {:>10} {:>15} {:>15} {:>10} {:>15}", "T [K]", "htst [1/s]", "eyring [1/s]", "kappa", "rate [1/s]");
+        for r in &reaction.rates {
+            println!("    {:10.2} {:15.6e} {:15.6e} {:10.4} {:15.6e}", r.temperature, r.htst, r.eyring, r.kappa, r.rate);
+        }
+        if let Some((activation_energy, prefactor)) = reaction.arrhenius {
+            println!("    arrhenius fit: activation energy {:15.10}, prefactor {:e} 1/s", activation_energy, prefactor);
+        }
+        reactions.push((*name, reaction));
+    }
+    let table: Vec<(&str, &Reaction)> = reactions.iter().map(|(name, reaction)| (*name, reaction)).collect();
+    if let Err(err) = rates::save_arrhenius_csv("images/arrhenius.csv", &table) {
+        println!("Could not save the arrhenius table!");
+        println!("Error: {}", err);
+    }
+}
+
+///the minimum a branch of the IRC ended in, relaxed like the ends of the path to get rid of the last
+/// bit of force the IRC stops at
+fn irc_end(branch: &irc::Branch, chain: &Chain, pes: &PES, convergence_limit: f64) -> Result<Point, String> {
+    if !branch.converged {
+        return Err("the irc ran out of steps before it reached a minimum".to_string());
+    }
+    let mut p = branch.points[branch.points.len() - 1];
+    p.move_to_minimum(pes, chain.config.optimizer, &chain.config.minimizer, convergence_limit);
+    Ok(p)
+}
+
+///relax many points spread over the image area and report the distinct minima they end up in
+fn find_minima() {
+    let mut start_time = SystemTime::now();
+    let config = load_config();
+    println!("Searching for minima...");
+    ensure_image_directory();
+
+    let area = config.image;
+    let minima = Minima::find(&config.pes, &config.minima, &area, config.path.optimizer, &config.path.minimizer,
+                             config.convergence_limit, config.stationary_gradient);
+    let seeds: usize = minima.minima.iter().map(|m| m.basin.len()).sum::<usize>() + minima.discarded;
+    print!("Relaxing {} points took: ", seeds);
+    print_elapsed_time(&mut start_time);
+
+    println!("found {} minima, {} points were discarded", minima.minima.len(), minima.discarded);
+    for (i, m) in minima.minima.iter().enumerate() {
+        println!("{:4}: ({:15.10}|{:15.10}) with energy: {:15.10} basin: {:5.1}% of the points",
+                 i, m.position.x, m.position.y, m.energy, 100.0 * m.basin.len() as f64 / seeds as f64);
+        print_characterization(&config.pes, m.position, config.stationary_gradient);
+    }
+
+    let results = [
+        minima.save_csv("images/minima.csv"),
+        minima.save_json("images/minima.json"),
+    ];
+    for result in results.iter() {
+        if let Err(err) = result {
+            println!("Could not save the minima!");
+            println!("Error: {}", err);
+        }
+    }
+    let img = Image::new(area, &config.pes);
+    minima.paint("images/basins.png", &img, area.point_size);
+}
+
+///find the minima, connect every pair of neighbors with a path and report the routes with the
+/// lowest barriers
+fn network() {
+    let mut start_time = SystemTime::now();
+    let config = load_config();
+    println!("Building the reaction network...");
+    ensure_image_directory();
+
+    let area = config.image;
+    let pes = config.pes;
+    let minima = Minima::find(&pes, &config.minima, &area, config.path.optimizer, &config.path.minimizer,
+                             config.convergence_limit, config.stationary_gradient);
+    println!("found {} minima:", minima.minima.len());
+    for (i, m) in minima.minima.iter().enumerate() {
+        println!("{:4}: ({:15.10}|{:15.10}) with energy: {:15.10}", i, m.position.x, m.position.y, m.energy);
+        print_characterization(&pes, m.position, config.stationary_gradient);
+    }
+    print!("Finding the minima took: ");
+    print_elapsed_time(&mut start_time);
+
+    let relaxation = Relaxation {
+        convergence: &config.convergence,
+        convergence_limit: config.convergence_limit,
+        stability: &config.stability,
+        domain: &area,
+    };
+    let mut network = Network::new(&minima);
+    for (from, to) in Network::neighbors(&minima, &config.network) {
+        print!("connecting {:4} and {:4}: ", from, to);
+        match network.connect(&pes, from, to, &config.path, &relaxation) {
+            Ok(c) => print!("saddle at ({:15.10}|{:15.10}) with energy: {:15.10} barriers: {:15.10} {:15.10}, took: ",
+                            c.saddle.x, c.saddle.y, c.energy, c.forward_barrier, c.reverse_barrier),
+            Err(err) => print!("no direct connection, {}, took: ", err),
+        }
         print_elapsed_time(&mut start_time);
+    }
+    for (c, connection) in network.connections.iter().enumerate() {
+        print!("saddle point of connection {} ({} -- {}): ", c, connection.from, connection.to);
+        print_characterization(&pes, connection.saddle, config.stationary_gradient);
+    }
 
-        // stop the loop if the last iteration was barely able to improve the situation
-        if last_energy - energy < config.convergence_limit {
-            break;
+    let routes: Vec<[usize; 2]> = match config.network.route {
+        Some(route) => vec![route],
+        None => (1..network.nodes.len()).map(|i| [0, i]).collect(),
+    };
+    let mut chosen = None;
+    for [from, to] in routes {
+        match network.route(from, to) {
+            Some(route) => {
+                let nodes: Vec<String> = route.nodes.iter().map(|n| n.to_string()).collect();
+                println!("route from {} to {}: {} with the highest saddle point at: {:15.10} (barrier: {:15.10})",
+                         from, to, nodes.join(" -> "), route.highest, route.highest - network.nodes[from].energy);
+                if config.network.route.is_some() {
+                    chosen = Some(route);
+                }
+            }
+            None => println!("there is no route from {} to {}", from, to),
+        }
+    }
+
+    let tree = Tree::new(&network, &config.disconnectivity);
+    let order: Vec<String> = tree.order.iter().map(|m| m.to_string()).collect();
+    println!("disconnectivity graph with {} levels, minima from left to right: {}", tree.thresholds.len(), order.join(" "));
+
+    let results = [
+        network.save_json("images/network.json"),
+        network.save_dot("images/network.dot", chosen.as_ref()),
+        tree.save_json("images/disconnectivity.json"),
+    ];
+    for result in results.iter() {
+        if let Err(err) = result {
+            println!("Could not save the network!");
+            println!("Error: {}", err);
+        }
+    }
+    let img = Image::new(area, &pes);
+    network.paint("images/network.png", &img, area.point_size);
+    tree.paint("images/disconnectivity.png");
+}
+
+///compare the analytic gradient of the configured PES with finite differences on a grid over the image area
+fn check_gradient() {
+    let config = load_config();
+    let area = config.image;
+    let step = 1e-5 * area.width.max(area.height);
+    let check = gradient_check::check_gradient(&config.pes, &area.grid(100, 100), step);
+
+    println!("Compared the analytic gradient with finite differences (step {:e}) at {} points.",
+             step, check.points_checked);
+    println!("largest deviation: {:e}", check.max_deviation);
+    println!("       at point:   ({:15.10}|{:15.10})", check.worst_point.x, check.worst_point.y);
+    println!("       analytic:   ({:15.10}|{:15.10})", check.analytic.x, check.analytic.y);
+    println!("       numeric:    ({:15.10}|{:15.10})", check.numeric.x, check.numeric.y);
+
+    if let Some((deviation, worst)) = gradient_check::check_hessian(&config.pes, &area.grid(100, 100), step) {
+        println!("Compared the analytic hessian with finite differences of the gradient.");
+        println!("largest deviation: {:e}", deviation);
+        println!("       at point:   ({:15.10}|{:15.10})", worst.x, worst.y);
+    }
+}
+
+///compare the result with the stationary points of the Müller-Brown surface known from the literature
+fn print_muller_brown_comparison(chain: &Chain, pes: &PES) {
+    println!("Comparison with the literature values of the Müller-Brown surface (energies scaled):");
+    let ends = [("start", chain.elements[0]), ("end", chain.elements[chain.elements.len() - 1])];
+    for (name, p) in ends.iter() {
+        let (reference, energy) = MullerBrown::closest(&MINIMA, *p);
+        println!("{:>7}: ({:10.6}|{:10.6}) E = {:15.10}   minimum: ({:10.6}|{:10.6}) E = {:15.10}",
+                 name, p.x, p.y, pes.energy_at(*p), reference.x, reference.y, pes.scale * energy);
+    }
+    let p = chain.elements[chain.highest_image(pes)];
+    let (reference, energy) = MullerBrown::closest(&SADDLES, p);
+    println!("{:>7}: ({:10.6}|{:10.6}) E = {:15.10}   saddle:  ({:10.6}|{:10.6}) E = {:15.10}",
+             "highest", p.x, p.y, pes.energy_at(p), reference.x, reference.y, pes.scale * energy);
+}
+
+///hash of everything in the config that influences the simulation. The image settings and the
+/// checkpoint settings themselves are left out, so they can be changed before resuming. The image
+/// area the elements have to stay in goes into the checkpoint instead
+fn simulation_hash(config: &Config) -> u64 {
+    let relevant = (config.convergence_limit, &config.convergence, &config.stability, &config.pes, &config.path);
+    checkpoint::config_hash(&serde_json::ser::to_string(&relevant).unwrap())
+}
+
+fn save_checkpoint(file: &str, config_hash: u64, domain: &ImageConfig, state: &LoopState, chain: &Chain) {
+    let checkpoint = Checkpoint { config_hash, domain: *domain, state: *state, chain: chain.clone() };
+    if let Err(err) = checkpoint.save(file) {
+        // not being able to save a checkpoint is no reason to stop the simulation
+        println!("Could not save the checkpoint '{}'!", file);
+        println!("Error: {}", err);
+    }
+}
+
+fn resume_checkpoint(file: &str, config_hash: u64) -> (Chain, LoopState, ImageConfig) {
+    match Checkpoint::load(file) {
+        Ok(checkpoint) => {
+            if checkpoint.config_hash != config_hash {
+                println!("The checkpoint '{}' was made with a different config!", file);
+                println!("Only the image and checkpoint settings may change before resuming a simulation.");
+                exit(5);
+            }
+            println!("Resuming from checkpoint '{}' at iteration {}.", file, checkpoint.chain.iteration);
+            (checkpoint.chain, checkpoint.state, checkpoint.domain)
+        }
+        Err(err) => {
+            println!("Could not resume from the checkpoint '{}'!", file);
+            println!("Error: {}", err);
+            exit(5);
         }
     }
 }
@@ -96,11 +591,16 @@ fn load_config() -> Config {
     match fs::read_to_string("MEP_config.txt") {
         Ok(data) => {
             println!("Read config file 'MEP_config.txt'.");
-            match serde_json::de::from_str(&*data) {
+            match serde_json::de::from_str(&data) {
                 Ok(json) => {
+                    let json: Config = json;
+                    if let Err(err) = json.path.check() {
+                        println!("The config file is not valid!");
+                        println!("Error: {}", err);
+                        exit(1);
+                    }
                     println!("Successfully parsed the config file.");
-                    println!("Simulating...");
-                    return json;
+                    json
                 }
                 Err(err) => {
                     println!("Failed to parse JSON from the config file!");
@@ -119,7 +619,7 @@ fn load_config() -> Config {
             match create_sample_config_file() {
                 Ok(_) => {
                     println!("Created an example config file 'MEP_config.txt'!");
-                    println!("Simulating the example...");
+                    println!("Using the example...");
                     sample_config()
                 }
                 Err(err) => {
@@ -138,6 +638,10 @@ fn create_sample_config_file() -> std::io::Result<()> {
     fs::write("MEP_config.txt", sample_config_text())
 }
 
+fn default_stationary_gradient() -> f64 {
+    1e-3
+}
+
 fn sample_config_text() -> String {
     serde_json::ser::to_string_pretty(&sample_config()).unwrap()
 }
@@ -146,25 +650,35 @@ fn sample_config() -> Config {
     let stable_limit = 1e-4;
 
     let pes = PES {
-        scale: 0.7,
+        scale: 1.0,
+        model: Model::Gaussians,
         gaussians: vec![
-            Gaussian { a: -7.0, x0: 15.0, sig_x: 5.0, y0: 7.5, sig_y: 5.0 },
-            Gaussian { a: -13.0, x0: 23.0, sig_x: 2.0, y0: 5.0, sig_y: 2.0 },
-            Gaussian { a: -7.0, x0: 10.0, sig_x: 2.5, y0: 21.0, sig_y: 2.5 },
-            Gaussian { a: -7.0, x0: 7.0, sig_x: 2.5, y0: 18.0, sig_y: 2.5 },
-            Gaussian { a: 15.0, x0: 25.0, sig_x: 7.0, y0: 20.0, sig_y: 7.0 },
-            Gaussian { a: 20.0, x0: 20.0, sig_x: 10.0, y0: -5.0, sig_y: 1.0 },
-            Gaussian { a: 20.0, x0: -2.0, sig_x: 1.5, y0: 20.0, sig_y: 10.0 },
-            Gaussian { a: 10.0, x0: 0.0, sig_x: 3.0, y0: 3.0, sig_y: 3.0 }
+            Gaussian { a: -7.0, x0: 15.0, sig_x: 5.0, y0: 7.5, sig_y: 5.0, angle: 0.0 },
+            Gaussian { a: -13.0, x0: 23.0, sig_x: 2.0, y0: 5.0, sig_y: 2.0, angle: 0.0 },
+            Gaussian { a: -7.0, x0: 10.0, sig_x: 2.5, y0: 21.0, sig_y: 2.5, angle: 0.0 },
+            Gaussian { a: -7.0, x0: 7.0, sig_x: 2.5, y0: 18.0, sig_y: 2.5, angle: 0.0 },
+            Gaussian { a: 15.0, x0: 25.0, sig_x: 7.0, y0: 20.0, sig_y: 7.0, angle: 0.0 },
+            Gaussian { a: 20.0, x0: 20.0, sig_x: 10.0, y0: -5.0, sig_y: 1.0, angle: 0.0 },
+            Gaussian { a: 20.0, x0: -2.0, sig_x: 1.5, y0: 20.0, sig_y: 10.0, angle: 0.0 },
+            Gaussian { a: 10.0, x0: 0.0, sig_x: 3.0, y0: 3.0, sig_y: 3.0, angle: 0.0 }
         ],
     };
     let chain_config = ChainConfig {
         spring_constant: 0.0,
         pin_ends: true,
+        free_ends: false,
         relax_ends: true,
         start: Point { x: 23.0, y: 5.0 },
         end: Point { x: 8.0, y: 19.0 },
         elements: 50,
+        method: Method::Legacy,
+        climbing_image: None,
+        growth_interval: 10,
+        optimizer: OptimizerConfig::SteepestDescent { step_size: 0.35 },
+        minimizer: MinimizerConfig::default(),
+        waypoints: vec![],
+        path_file: None,
+        spline: false,
     };
 
     let image_config = ImageConfig {
@@ -181,19 +695,33 @@ fn sample_config() -> Config {
 
     Config {
         convergence_limit: stable_limit,
+        stationary_gradient: default_stationary_gradient(),
+        convergence: ConvergenceConfig::default(),
+        stability: StabilityConfig::default(),
+        checkpoint: CheckpointConfig::default(),
+        dimer: None,
+        irc: None,
+        rates: None,
+        minima: MinimaConfig::default(),
+        network: NetworkConfig::default(),
+        disconnectivity: DisconnectivityConfig::default(),
         pes: pes.clone(),
         path: chain_config,
         image: image_config,
     }
 }
 
-fn arg_parse() {
+fn arg_parse() -> Mode {
     let args: Vec<String> = env::args().collect();
     if args.len() <= 1 {  // <=1, because the first one is always the program name
-        return;
+        return Mode::Simulate(None);
     }
     // we got an argument
     match &args[1][..] {
+        "--check-gradient" => Mode::CheckGradient,
+        "--find-minima" => Mode::FindMinima,
+        "--network" => Mode::Network,
+        "--resume" if args.len() > 2 => Mode::Simulate(Some(args[2].clone())),
         "--explain-json" => {
             print_json_help();
             exit(0);
@@ -210,12 +738,31 @@ fn print_help_text() {
     Simulation of finding the Minimum Energy Path on a Potential Energy Surface
 
     Reads a config file (example one produced on first run) and saves resulting
-    images to ./images/ (directory is created if it doesn't exist)
+    images to ./images/ (directory is created if it doesn't exist). The energy
+    profile along the final path is saved there as well (profile.csv,
+    profile.json and the plot profile.png), together with a smooth cubic
+    interpolation of it (profile_interpolated.csv).
+    At the end the ends and the highest image are classified as minimum or
+    saddle point by the eigenvalues of the hessian.
+    If configured, the saddle point is then refined with the dimer method and
+    the intrinsic reaction coordinate is traced from it (irc.csv, irc.json and
+    irc.png). Rate constants can be estimated from the barrier as well
+    (arrhenius.csv).
 
     usage:
     minimum_energy_path                   simulate according to the config file
     minimum_energy_path ( --help | -h )   show this help
     minimum_energy_path --explain-json    explain the config file
+    minimum_energy_path --resume <file>   continue the simulation from a checkpoint file
+    minimum_energy_path --check-gradient  compare the analytic gradient and hessian of the PES
+                                          with finite differences over the image area
+    minimum_energy_path --find-minima     relax points all over the image area and list the
+                                          minima they end up in (minima.csv, minima.json and
+                                          the basins of attraction in basins.png)
+    minimum_energy_path --network         find the minima, run a path between every pair of
+                                          neighbors and report the routes with the lowest
+                                          barriers (network.json, network.dot, network.png and
+                                          the disconnectivity graph disconnectivity.png)
     "#;
     println!("{}", help_text);
 }
@@ -238,17 +785,136 @@ fn print_json_help() {
 
     {
       "convergence_limit": 1e-3,        // if the change in average energy after a simulation step is
-                                        // smaller than this value the simulation will end.
+                                        // smaller than this value, up or down, for energy_iterations
+                                        // steps in a row the simulation will end.
+      "stationary_gradient": 1e-3,      // Optional, points with a larger gradient norm are reported
+                                        // as not stationary instead of as a minimum or saddle point,
+                                        // and --find-minima discards them.
+      "convergence": {                  // Optional, more ways to decide when the simulation is done.
+        "energy": true,                 // If the convergence_limit above should be used at all. The
+                                        // average energy can stall on flat regions or oscillate.
+        "energy_iterations": 5,         // For how many iterations in a row the energy has to change
+                                        // by less than the convergence_limit, up or down.
+        "max_force": 1e-3,              // End once no point feels a force perpendicular to the path
+                                        // larger than this,
+        "rms_force": 1e-4,              // or once the root mean square of those forces is below this,
+        "max_displacement": 1e-6,       // or once no point moved further than this in one iteration,
+        "max_iterations": 1000          // or after this many iterations no matter what.
+      },                                // Each of these can be left out or set to null. The program
+                                        // reports which criterion ended the simulation.
+                                        // max_iterations defaults to 1000, null removes the limit.
+      "stability": {                    // Optional, how instabilities are detected and handled. If
+                                        // a point ends up with coordinates that are not a number,
+                                        // the average energy jumps up or a point leaves the image
+                                        // area, the simulation goes back one iteration and retries
+                                        // with a smaller step.
+        "max_energy_rise": 5.0,         // How much the average energy may rise in one iteration. If
+                                        // left out the energy span of the initial path and of the
+                                        // straight line between its ends is used. Rises below a
+                                        // billionth of the energies are always accepted.
+        "keep_in_domain": true,         // If leaving the image area counts as unstable.
+        "step_reduction": 0.5,          // By how much the step of the optimizer is scaled each time.
+        "max_retries": 5                // How often in a row this may happen before giving up.
+      },
+      "checkpoint": {                   // Optional, the state of the simulation is saved regularly
+        "interval": 100,                // every this many iterations and at the end. null disables
+        "file": "checkpoint.json"       // it. Run with --resume <file> to continue from a checkpoint.
+      },                                // Only the image, checkpoint and the analysis settings
+                                        // (dimer, irc, rates) may be changed before resuming. The
+                                        // elements still have to stay in the image area the
+                                        // simulation started with.
+      "dimer": {                        // Optional, after the simulation the highest point of the path
+                                        // is moved onto the exact saddle point with the dimer method.
+                                        // Only the gradient is needed for this. null disables it and
+                                        // {} uses the defaults given here.
+        "separation": 1e-3,             // The distance between the center and the end of the dimer.
+        "max_force": 1e-6,              // Done once the force at the center is smaller than this,
+        "max_iterations": 1000,         // or give up after this many steps.
+        "max_rotations": 4,             // How often the dimer is turned towards the lowest curvature
+        "rotation_tolerance": 1e-3,     // before every step, unless the turn is smaller than this
+                                        // angle in radians.
+        "optimizer": null               // How the center is moved, the same options as for the path
+                                        // below. Uses the optimizer of the path if left out.
+      },
+      "irc": {                          // Optional, after the simulation (and the dimer) the path of
+                                        // steepest descent is followed from the saddle point down to
+                                        // both sides. It is saved as images/irc.csv and irc.json and
+                                        // drawn in orange over the final path in images/irc.png.
+                                        // null disables it and {} uses the defaults given here.
+        "saddle": {"x": 9.3, "y": 13.0},// Where to start. If left out the saddle point of the dimer
+                                        // is used, or the highest point of the path without it.
+        "integrator": "rk4",            // How to step: "euler", "rk4" or "gonzalez_schlegel", which
+                                        // goes half a step and then finds the lowest point on the
+                                        // circle around it.
+        "step_size": 1e-2,              // The length of each step.
+        "displacement": 1e-2,           // How far to step off the saddle along the direction of
+                                        // negative curvature, one step_size if left out.
+        "min_force": 1e-4,              // Each side ends once the force is below this or the energy
+        "max_steps": 10000              // would rise again, or after this many steps.
+      },
+      "rates": {                        // Optional, estimate the rate constants from both ends of the
+                                        // path over the saddle point (of the dimer if it is used)
+                                        // with harmonic transition state theory. The results are
+                                        // printed and saved to images/arrhenius.csv.
+        "temperatures": [300.0, 400.0], // In kelvin.
+        "masses": {"x": 1.0, "y": 1.0}, // The mass moving along each coordinate in atomic mass units.
+        "energy_unit": "kj_per_mol",    // What the energies of the PES mean: "kj_per_mol",
+                                        // "kcal_per_mol", "ev" or "hartree".
+        "length_unit": "angstrom",      // What the coordinates mean: "angstrom", "bohr" or
+                                        // "nanometer".
+        "wigner": false                 // If the Wigner correction for tunneling should be applied,
+                                        // it uses the imaginary frequency of the saddle point.
+      },                                // The table also shows the classical (Vineyard) rate and
+                                        // an Arrhenius fit over all temperatures.
+      "minima": {                       // Optional, only used with --find-minima. Points spread over
+                                        // the image area are relaxed like the ends of the path and
+                                        // the distinct minima they end up in are listed.
+        "seeding": "grid",              // How the points are spread: "grid", "random" or "sobol"
+                                        // (random, but covering the area more evenly).
+        "seeds": 400,                   // How many points, a grid rounds this up to a square number.
+        "random_seed": 1,               // The same seed always gives the same random points.
+        "tolerance": 0.027,             // Relaxed points closer than this are the same minimum. If
+                                        // left out a thousandth of the larger side of the image.
+                                        // Points without a barrier on the straight line between
+                                        // them are the same minimum as well. That line is checked
+                                        // every tolerance, narrower barriers are missed.
+        "keep_in_domain": true          // If minima outside the image area are dropped.
+      },
+      "network": {                      // Optional, only used with --network. The minima are found as
+                                        // with --find-minima and every pair of neighbors (no other
+                                        // minimum lies in the circle between them) is connected by
+                                        // a path with the settings of the path section below. The
+                                        // highest point of each path is the saddle point between
+                                        // the two minima.
+        "max_distance": 10.0,           // Optional, minima further apart are never connected.
+        "route": [0, 2]                 // Optional, find the route between these two minima (by
+                                        // index, lowest energy first) whose highest saddle point is
+                                        // lowest. If left out the routes from the lowest minimum to
+                                        // all others are shown.
+      },
+      "disconnectivity": {              // Optional, the disconnectivity graph drawn by --network. At
+                                        // each level the minima that can reach each other without
+                                        // going higher are grouped together.
+        "levels": 20,                   // How many evenly spaced levels from the lowest minimum to
+                                        // just above the highest saddle point,
+        "thresholds": [-10.0, -5.0]     // or the energies of the levels themselves.
+      },
       "pes": {
-        "scale": 0.1,                   // by how much the PES should be scaled. As we are doing a
-                                        // simple gradient descent this is the way to set the speed
-                                        // and inversely to that the stability of the simulation
+        "scale": 1.0,                   // by how much the energies of the PES should be scaled. This
+                                        // only changes the units, the speed of the simulation is set
+                                        // with the optimizer in the path section.
+
+        "model": "gaussians",           // Which analytic surface the gaussians are added onto. Either
+                                        // "gaussians" for none or "muller_brown" for the Müller-Brown
+                                        // benchmark surface. Can be omitted, defaults to "gaussians".
 
         "gaussians": [                  // The PES is a linear combination of gaussian functions.
           {"a": -7.0,                   // Each function has a preexponential factor a, the peak
             "x0": 15.0,"sig_x": 5.0,    // (or valley) position (x0|y0) as well as the standard
-            "y0": 7.5,"sig_y": 5.0      // deviation in each direction.
-          },
+            "y0": 7.5,"sig_y": 5.0,     // deviation in each direction.
+            "angle": 0.0                // Optionally the axes of sig_x and sig_y can be rotated
+          },                            // counter clockwise by this angle in degrees. This allows
+                                        // for diagonal valleys and ridges. Defaults to 0.
           //....
           {"a": 10.0,
             "x0": 0.0,"sig_x": 3.0,
@@ -259,8 +925,16 @@ fn print_json_help() {
       "path": {                         // The path describes the inital guess for the MEP.
         "spring_constant": 0.3,         // How much the spring forces should be rescaled. Setting this
                                         // to zero disables springs. Large values lead to instability.
+                                        // With the legacy method and steepest descent the springs are
+                                        // divided by step_size, so they move the points as they used to.
         "pin_ends": true,               // If the end are allowed to move. The ends do not have a
                                         // proper tangent.
+        "free_ends": false,             // Only used if the ends are not pinned. Lets them slide
+                                        // along the contour line of their starting energy, pulled by
+                                        // the spring towards their neighbor (free end NEB). Useful
+                                        // if only the region around the saddle point matters and
+                                        // the ends sit at arbitrary energies. Otherwise they follow
+                                        // the force perpendicular to the chain.
         "relax_ends": true,             // If the ends should relax to their local minimum before they
                                         // are used to construct the line of points. This can greatly
                                         // improve results. Convergence criterium for having found the
@@ -273,9 +947,72 @@ fn print_json_help() {
           "x": 9.5,
           "y": 19.5
         },
-        "elements": 50                  // How many points are placed along the line. The program can
+        "elements": 50,                 // How many points are placed along the line. The program can
                                         // deal with many, but large numbers (more than 100) lead to
                                         // severe instability.
+        "method": "legacy",             // How the points are moved. "legacy" moves them along the
+                                        // gradient perpendicular to the line between their neighbors
+                                        // and springs pull them towards the middle of the neighbors.
+                                        // "neb" is a proper nudged elastic band: the gradient
+                                        // perpendicular to the improved (upwinding) tangent plus a
+                                        // spring force along the tangent with the spring_constant.
+                                        // "string" is the zero temperature string method: the points
+                                        // follow the full gradient and are then redistributed evenly
+                                        // along the path. It ignores the spring_constant and stays
+                                        // stable for many points. "growing_string" starts with only
+                                        // the two ends and adds points from both sides until they
+                                        // meet, then continues as "string". This needs no good
+                                        // initial guess. Defaults to "legacy".
+        "climbing_image": 20,           // Optional. After this many iterations the element with the
+                                        // highest energy stops feeling the springs and climbs up
+                                        // along the path until it sits exactly on the saddle point.
+                                        // Its position and energy are reported at the end. Leave it
+                                        // out or set it to null to disable the climbing image.
+        "growth_interval": 10,          // Only for "growing_string": how many iterations pass between
+                                        // adding new points at both tips. Defaults to 10, has to be
+                                        // at least 1.
+        "optimizer": {                  // How the points are moved once the forces on them are known.
+          "type": "steepest_descent",   // "steepest_descent" moves every point by step_size times the
+          "step_size": 0.1              // force. This sets the speed and inversely to that the
+                                        // stability of the simulation.
+                                        // {"type": "quick_min", "time_step": 0.3} runs damped
+                                        // dynamics that only keep the velocity along the force.
+                                        // {"type": "fire", "time_step": 0.1, "max_time_step": 1.0}
+                                        // is the FIRE optimizer with an adaptive time step. It also
+                                        // takes "min_steps" (5), "increase" (1.1), "decrease" (0.5),
+                                        // "alpha" (0.1) and "alpha_decrease" (0.99).
+                                        // Both of them use velocity Verlet dynamics with unit masses.
+                                        // {"type": "lbfgs", "memory": 10, "max_step": 0.2} is a
+                                        // limited memory BFGS on all points of the path at once. It
+                                        // remembers the last "memory" steps and no point moves
+                                        // further than "max_step" in one iteration. Best suited for
+                                        // smooth surfaces.
+                                        // The same optimizer is used to relax the ends. Defaults to
+                                        // steepest descent with a step_size of 1.
+        },
+        "minimizer": {                  // Optional, how the ends (and the points of --find-minima)
+                                        // are relaxed into their minimum. Steps that would raise
+                                        // the energy are halved until they don't.
+          "step": "optimizer",          // "optimizer" uses the optimizer above, "newton" uses the
+                                        // hessian of the PES and "bfgs" builds an approximation of
+                                        // it from the gradients.
+          "max_iterations": 1000,       // Give up after this many steps, the failure is reported.
+          "max_force": 1e-3,            // Done once the force is below this. With null it is done
+                                        // once the energy changes by less than the convergence_limit
+                                        // instead, even if the force is still large.
+          "max_step": 0.5               // Optional, the longest newton or bfgs step.
+        },
+        "waypoints": [                  // Optional. Instead of a straight line from start to end the
+          {"x": 15.0, "y": 10.0}        // initial path passes through these points in order. The
+        ],                              // points are spread evenly along this path.
+        "path_file": "path.csv",        // Optional. Read the waypoints from this file instead. A .csv
+                                        // file has one "x,y" pair per line, only the first line may
+                                        // be a header. Anything else is read as a JSON list of points
+                                        // like the waypoints above. A file without points or with a
+                                        // line that can't be read is an error.
+        "spline": false                 // If the path through the waypoints should be a smooth spline
+                                        // instead of straight lines. Defaults to false.
+                                        // The growing string ignores all of these.
       },
       "image": {                        // This section configures the images that are saved
         "contour_lines": 10,            // how many "contour lines" should be shown. They are not real
@@ -0,0 +1,269 @@
+use std::fs;
+use crate::point::Point;
+use crate::pes::PotentialEnergySurface;
+use crate::image::{Image, ImageConfig};
+use crate::optimizer::OptimizerConfig;
+use crate::minimizer::MinimizerConfig;
+use crate::stationary::{Characterization, Kind};
+
+///how the starting points for the search are spread over the image area
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Seeding {
+    ///an evenly spaced grid
+    #[default]
+    Grid,
+    ///uniformly random points
+    Random,
+    ///a Sobol sequence, covers the area more evenly than random points
+    Sobol,
+}
+
+///how the minima of the surface are searched for
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct MinimaConfig {
+    #[serde(default)]
+    pub(crate) seeding: Seeding,
+    ///how many points are relaxed, for a grid this is rounded up to a square number
+    #[serde(default = "default_seeds")]
+    pub(crate) seeds: usize,
+    ///the seed of the random number generator, the same seed gives the same points
+    #[serde(default = "default_random_seed")]
+    pub(crate) random_seed: u64,
+    ///relaxed points closer than this, or without a barrier between them, are the same minimum. If
+    /// left out a thousandth of the larger side of the image area is used
+    #[serde(default)]
+    pub(crate) tolerance: Option<f64>,
+    ///if minima outside the image area should be dropped
+    #[serde(default = "default_keep_in_domain")]
+    pub(crate) keep_in_domain: bool,
+}
+
+fn default_seeds() -> usize {
+    400
+}
+
+fn default_random_seed() -> u64 {
+    1
+}
+
+fn default_keep_in_domain() -> bool {
+    true
+}
+
+impl Default for MinimaConfig {
+    fn default() -> Self {
+        MinimaConfig {
+            seeding: Seeding::default(),
+            seeds: default_seeds(),
+            random_seed: default_random_seed(),
+            tolerance: None,
+            keep_in_domain: default_keep_in_domain(),
+        }
+    }
+}
+
+///a minimum of the surface, together with the seeds that relaxed into it
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Minimum {
+    pub(crate) position: Point,
+    pub(crate) energy: f64,
+    ///the starting points that ended up in this minimum, a sample of its basin of attraction
+    pub(crate) basin: Vec<Point>,
+}
+
+///all minima that were found, the lowest first
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Minima {
+    pub(crate) minima: Vec<Minimum>,
+    ///seeds that relaxed into something else than a minimum or left the image area
+    pub(crate) discarded: usize,
+}
+
+impl Minima {
+    ///relaxes every seed like the ends of the path and groups the results into distinct minima.
+    /// Points whose gradient norm stays above stationary_gradient are discarded
+    pub fn find(pes: &dyn PotentialEnergySurface, config: &MinimaConfig, area: &ImageConfig,
+                optimizer: OptimizerConfig, minimizer: &MinimizerConfig, convergence_limit: f64,
+                stationary_gradient: f64) -> Self {
+        let tolerance = config.tolerance.unwrap_or(1e-3 * area.width.max(area.height));
+        let mut minima: Vec<Minimum> = Vec::new();
+        let mut discarded = 0;
+        for seed in seeds(config, area) {
+            let mut p = seed;
+            p.move_to_minimum(pes, optimizer, minimizer, convergence_limit);
+            let is_minimum = match Characterization::at(pes, p, stationary_gradient) {
+                Some(characterization) => characterization.kind == Kind::Minimum,
+                None => pes.gradient_at(p).norm() <= stationary_gradient,
+            };
+            if p.x.is_nan() || p.y.is_nan() || !is_minimum || (config.keep_in_domain && !area.contains(p)) {
+                discarded += 1;
+                continue;
+            }
+            let same = |m: &&mut Minimum| m.position.distance_sq(p) < tolerance.powi(2) || !barrier_between(pes, m.position, p, tolerance);
+            match minima.iter_mut().find(same) {
+                Some(minimum) => minimum.basin.push(seed),
+                None => minima.push(Minimum { position: p, energy: pes.energy_at(p), basin: vec![seed] }),
+            }
+        }
+        minima.sort_by(|a, b| a.energy.partial_cmp(&b.energy).unwrap());
+        Minima { minima, discarded }
+    }
+
+    pub fn save_csv(&self, file: &str) -> std::io::Result<()> {
+        let mut data = String::from("index,x,y,energy,basin_size\n");
+        for (i, m) in self.minima.iter().enumerate() {
+            data += &format!("{},{},{},{},{}\n", i, m.position.x, m.position.y, m.energy, m.basin.len());
+        }
+        fs::write(file, data)
+    }
+
+    pub fn save_json(&self, file: &str) -> std::io::Result<()> {
+        fs::write(file, serde_json::ser::to_string_pretty(self).unwrap())
+    }
+
+    ///draws every seed in the color of the minimum it ended up in, the minima themselves are black
+    pub fn paint(&self, file: &str, img: &Image, point_size: f64) {
+        let mut buffer = img.canvas();
+        for (i, minimum) in self.minima.iter().enumerate() {
+            let color = basin_color(i);
+            for &seed in &minimum.basin {
+                img.draw_circle(&mut buffer, seed, point_size, &color);
+            }
+        }
+        for minimum in &self.minima {
+            img.draw_circle(&mut buffer, minimum.position, 2.0 * point_size, &[0, 0, 0]);
+        }
+        buffer.save(file).unwrap();
+    }
+}
+
+///if the energy along the straight line from a to b goes up and then down again somewhere. Without a
+/// barrier both points sit in the same well, even if they stopped relaxing far apart on a flat bottom.
+/// The line is sampled every spacing, so a barrier narrower than that can be missed
+fn barrier_between(pes: &dyn PotentialEnergySurface, a: Point, b: Point, spacing: f64) -> bool {
+    const MIN_SAMPLES: usize = 20;
+    const MAX_SAMPLES: usize = 100_000;
+    let samples = ((a.distance_sq(b).sqrt() / spacing).ceil() as usize).clamp(MIN_SAMPLES, MAX_SAMPLES);
+    let energies: Vec<f64> = (0..=samples)
+        .map(|i| pes.energy_at(a + (i as f64 / samples as f64) * (b - a)))
+        .collect();
+    let mut rising = false;
+    for pair in energies.windows(2) {
+        if pair[1] > pair[0] {
+            rising = true;
+        } else if rising && pair[1] < pair[0] {
+            return true;
+        }
+    }
+    false
+}
+
+///a few colors that are easy to tell apart, repeated if there are more minima
+fn basin_color(i: usize) -> [u8; 3] {
+    const COLORS: [[u8; 3]; 8] = [
+        [230, 25, 75], [60, 180, 75], [0, 130, 200], [245, 130, 48],
+        [145, 30, 180], [70, 240, 240], [240, 50, 230], [210, 245, 60],
+    ];
+    COLORS[i % COLORS.len()]
+}
+
+///the starting points over the image area
+fn seeds(config: &MinimaConfig, area: &ImageConfig) -> Vec<Point> {
+    let to_area = |(u, v): (f64, f64)| Point { x: area.x0 + u * area.width, y: area.y0 + v * area.height };
+    match config.seeding {
+        Seeding::Grid => {
+            let n = (config.seeds as f64).sqrt().ceil() as usize;
+            area.grid(n, n)
+        }
+        Seeding::Random => {
+            let mut state = config.random_seed.max(1);
+            (0..config.seeds).map(|_| to_area((random(&mut state), random(&mut state)))).collect()
+        }
+        Seeding::Sobol => sobol(config.seeds).into_iter().map(to_area).collect(),
+    }
+}
+
+///xorshift64*, a uniform number in [0, 1)
+fn random(state: &mut u64) -> f64 {
+    *state ^= *state >> 12;
+    *state ^= *state << 25;
+    *state ^= *state >> 27;
+    (state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+///the first n points of the two dimensional Sobol sequence, skipping the corner at zero
+fn sobol(n: usize) -> Vec<(f64, f64)> {
+    const BITS: usize = 32;
+    // direction numbers: the first dimension is the van der Corput sequence, the second one comes
+    // from the primitive polynomial x + 1
+    let mut first = [0u32; BITS];
+    let mut second = [0u32; BITS];
+    let mut m = 1u32;
+    for k in 0..BITS {
+        first[k] = 1 << (BITS - 1 - k);
+        if k > 0 {
+            m ^= m << 1;
+        }
+        second[k] = m << (BITS - 1 - k);
+    }
+    let scale = 1.0 / (1u64 << BITS) as f64;
+    let (mut x, mut y) = (0u32, 0u32);
+    (1..=n).map(|i| {
+        // gray code: flip the direction number of the lowest zero bit of the previous index
+        let bit = (i - 1).trailing_ones() as usize;
+        x ^= first[bit];
+        y ^= second[bit];
+        (x as f64 * scale, y as f64 * scale)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minimizer::StepKind;
+    use crate::muller_brown::{MullerBrown, MINIMA};
+
+    fn muller_brown_area() -> ImageConfig {
+        ImageConfig {
+            contour_lines: 20.0,
+            x0: -1.5,
+            y0: -0.5,
+            width: 2.7,
+            height: 2.5,
+            resolution_x: 270,
+            resolution_y: 250,
+            point_size: 0.01,
+            line_width: 0.002,
+        }
+    }
+
+    #[test]
+    fn finds_the_three_muller_brown_minima() {
+        let minimizer = MinimizerConfig { step: StepKind::Newton, max_force: Some(1e-6), ..MinimizerConfig::default() };
+        for &seeding in [Seeding::Grid, Seeding::Random, Seeding::Sobol].iter() {
+            let config = MinimaConfig { seeding, seeds: 100, ..MinimaConfig::default() };
+            let minima = Minima::find(&MullerBrown, &config, &muller_brown_area(), OptimizerConfig::default(),
+                                      &minimizer, 1e-9, 1e-3);
+
+            assert_eq!(minima.minima.len(), 3, "{:?}", seeding);
+            let seeds: usize = minima.minima.iter().map(|m| m.basin.len()).sum::<usize>() + minima.discarded;
+            assert_eq!(seeds, 100);
+            // sorted by energy, just like the known ones
+            for (found, &(position, energy)) in minima.minima.iter().zip(MINIMA.iter()) {
+                assert!(found.position.distance_sq(position).sqrt() < 1e-5, "{:?}: ({}|{})", seeding, found.position.x, found.position.y);
+                assert!((found.energy - energy).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn barrier_separates_the_muller_brown_minima() {
+        let spacing = 1e-3;
+        assert!(barrier_between(&MullerBrown, MINIMA[0].0, MINIMA[2].0, spacing));
+        assert!(barrier_between(&MullerBrown, MINIMA[2].0, MINIMA[1].0, spacing));
+        // both sides of one well
+        let offset = Point { x: 0.05, y: 0.0 };
+        assert!(!barrier_between(&MullerBrown, MINIMA[1].0 + -1.0 * offset, MINIMA[1].0 + offset, spacing));
+    }
+}
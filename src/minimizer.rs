@@ -0,0 +1,221 @@
+use std::fmt;
+use crate::point::Point;
+use crate::pes::{PotentialEnergySurface, Hessian};
+use crate::optimizer::{Optimizer, OptimizerConfig};
+
+///how a single point is moved towards its minimum
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepKind {
+    ///the optimizer of the path
+    #[default]
+    Optimizer,
+    ///Newton steps with the analytic hessian, BFGS steps if the surface has none
+    Newton,
+    ///quasi Newton steps with an approximate inverse hessian built from the gradients
+    Bfgs,
+}
+
+///how the ends of the path and other single points are relaxed into their minimum
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct MinimizerConfig {
+    #[serde(default)]
+    pub(crate) step: StepKind,
+    ///give up after this many steps
+    #[serde(default = "default_max_iterations")]
+    pub(crate) max_iterations: usize,
+    ///done once the force is smaller than this. If null the relaxation ends once the energy barely
+    /// changes instead, which also happens on a slope the optimizer crawls down slowly
+    #[serde(default = "default_max_force")]
+    pub(crate) max_force: Option<f64>,
+    ///Newton and BFGS steps are never longer than this
+    #[serde(default)]
+    pub(crate) max_step: Option<f64>,
+}
+
+fn default_max_iterations() -> usize {
+    1000
+}
+
+fn default_max_force() -> Option<f64> {
+    Some(1e-3)
+}
+
+impl Default for MinimizerConfig {
+    fn default() -> Self {
+        MinimizerConfig {
+            step: StepKind::default(),
+            max_iterations: default_max_iterations(),
+            max_force: default_max_force(),
+            max_step: None,
+        }
+    }
+}
+
+///why the relaxation of a point stopped
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stop {
+    ///the energy decreased by less than the convergence_limit, only used without max_force
+    Energy,
+    ///the force dropped below max_force
+    Force,
+    MaxIterations,
+    ///even a tiny step didn't lower the energy anymore
+    NoDescent,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Stop::Energy => "the energy barely changed",
+            Stop::Force => "the force is below max_force",
+            Stop::MaxIterations => "max_iterations reached",
+            Stop::NoDescent => "no step lowered the energy anymore",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+///what happened while relaxing a point
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct Minimization {
+    pub(crate) iterations: usize,
+    pub(crate) energy: f64,
+    pub(crate) gradient_norm: f64,
+    pub(crate) stop: Stop,
+}
+
+impl Minimization {
+    pub fn converged(&self) -> bool {
+        matches!(self.stop, Stop::Energy | Stop::Force)
+    }
+}
+
+impl fmt::Display for Minimization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} after {} steps with energy {:15.10} and gradient norm {:e}",
+               self.stop, self.iterations, self.energy, self.gradient_norm)
+    }
+}
+
+///how often a step that went uphill is halved before giving up
+const MAX_HALVINGS: usize = 50;
+
+///moves p downhill until one of the criteria is met. Steps that would raise the energy are never
+/// taken: the step is made smaller instead
+pub fn minimize(p: &mut Point, pes: &dyn PotentialEnergySurface, optimizer: OptimizerConfig,
+                config: &MinimizerConfig, convergence_limit: f64) -> Minimization {
+    let mut optimizer = Optimizer::new(optimizer);
+    // the approximate inverse hessian, unscaled until the first step tells us the curvature
+    let mut inverse = Hessian { xx: 1.0, xy: 0.0, yy: 1.0 };
+    let mut first = true;
+    let mut energy = pes.energy_at(*p);
+    let mut force = pes.gradient_at(*p);
+    let mut iterations = 0;
+
+    let stop = loop {
+        if let Some(max_force) = config.max_force {
+            if force.norm() < max_force {
+                break Stop::Force;
+            }
+        }
+        if iterations >= config.max_iterations {
+            break Stop::MaxIterations;
+        }
+
+        // find a step that goes downhill
+        let mut step = match config.step {
+            StepKind::Optimizer => Point { x: 0.0, y: 0.0 },
+            StepKind::Newton => match pes.hessian_at(*p) {
+                Some(hessian) => newton_step(hessian, force),
+                None => inverse.times(force),
+            },
+            StepKind::Bfgs => inverse.times(force),
+        };
+        if config.step != StepKind::Optimizer {
+            if let Some(max_step) = config.max_step {
+                if step.norm() > max_step {
+                    step = (max_step / step.norm()) * step;
+                }
+            }
+        }
+        let mut accepted = None;
+        let mut halvings = 0;
+        for _ in 0..MAX_HALVINGS {
+            let next = match config.step {
+                StepKind::Optimizer => {
+                    let mut position = [*p];
+                    optimizer.step(&mut position, &[force]);
+                    position[0]
+                }
+                _ => *p + step,
+            };
+            let next_energy = pes.energy_at(next);
+            if next_energy < energy {
+                accepted = Some((next, next_energy));
+                break;
+            }
+            // too far, try again with half the step
+            match config.step {
+                StepKind::Optimizer => optimizer.reduce_step(0.5),
+                _ => step = 0.5 * step,
+            }
+            halvings += 1;
+        }
+        let (next, next_energy) = match accepted {
+            Some(accepted) => accepted,
+            None => break Stop::NoDescent,
+        };
+        // the halving was only needed here, the next step may be as long as before again
+        if config.step == StepKind::Optimizer && halvings > 0 {
+            optimizer.reduce_step(2f64.powi(halvings));
+        }
+
+        let next_force = pes.gradient_at(next);
+        let s = next - *p;
+        // the change of the gradient, the force is the negative gradient
+        let y = force - next_force;
+        if y.dot_product(s) > 0.0 {
+            if first {
+                let scale = y.dot_product(s) / y.dot_product(y);
+                inverse = Hessian { xx: scale, xy: 0.0, yy: scale };
+                first = false;
+            }
+            inverse = bfgs_update(inverse, s, y);
+        }
+
+        iterations += 1;
+        let energy_change = energy - next_energy;
+        *p = next;
+        energy = next_energy;
+        force = next_force;
+        if config.max_force.is_none() && energy_change < convergence_limit {
+            break Stop::Energy;
+        }
+    };
+
+    Minimization { iterations, energy, gradient_norm: force.norm(), stop }
+}
+
+///the Newton step for the force. Along directions of negative curvature it goes downhill instead of
+/// up towards the maximum
+fn newton_step(hessian: Hessian, force: Point) -> Point {
+    let (values, vectors) = hessian.eigen();
+    values.iter().zip(vectors.iter())
+        .filter(|(value, _)| **value != 0.0)
+        .map(|(value, &vector)| (force.dot_product(vector) / value.abs()) * vector)
+        .sum()
+}
+
+///the BFGS update of the inverse hessian after the step s changed the gradient by y
+fn bfgs_update(inverse: Hessian, s: Point, y: Point) -> Hessian {
+    let rho = 1.0 / y.dot_product(s);
+    let hy = inverse.times(y);
+    let c = rho * rho * y.dot_product(hy) + rho;
+    Hessian {
+        xx: inverse.xx - rho * 2.0 * hy.x * s.x + c * s.x * s.x,
+        xy: inverse.xy - rho * (hy.x * s.y + s.x * hy.y) + c * s.x * s.y,
+        yy: inverse.yy - rho * 2.0 * hy.y * s.y + c * s.y * s.y,
+    }
+}
@@ -0,0 +1,81 @@
+use crate::point::Point;
+use crate::pes::{PotentialEnergySurface, Hessian};
+
+// parameters of the four terms
+// V(x,y) = sum_k A_k * exp(a_k (x-x0_k)^2 + b_k (x-x0_k)(y-y0_k) + c_k (y-y0_k)^2)
+// see K. Müller, L. D. Brown, Theoret. Chim. Acta 53, 75 (1979)
+const A: [f64; 4] = [-200.0, -100.0, -170.0, 15.0];
+const ALPHA: [f64; 4] = [-1.0, -1.0, -6.5, 0.7];
+const BETA: [f64; 4] = [0.0, 0.0, 11.0, 0.6];
+const GAMMA: [f64; 4] = [-10.0, -10.0, -6.5, 0.7];
+const X0: [f64; 4] = [1.0, 0.0, -0.5, -1.0];
+const Y0: [f64; 4] = [0.0, 0.5, 1.5, 1.0];
+
+///the known minima of the unscaled surface as (position, energy)
+pub const MINIMA: [(Point, f64); 3] = [
+    (Point { x: -0.558224, y: 1.441726 }, -146.699517),
+    (Point { x: 0.623499, y: 0.028038 }, -108.166724),
+    (Point { x: -0.050011, y: 0.466694 }, -80.767820),
+];
+
+///the known first order saddle points of the unscaled surface as (position, energy)
+pub const SADDLES: [(Point, f64); 2] = [
+    (Point { x: -0.822002, y: 0.624313 }, -40.664843),
+    (Point { x: 0.212487, y: 0.292988 }, -72.248894),
+];
+
+///The Müller-Brown surface, the standard benchmark for MEP methods. It can't be built from the
+/// axis aligned gaussians, because three of the four terms have a cross term in the exponent.
+#[derive(Debug, Copy, Clone)]
+pub struct MullerBrown;
+
+impl MullerBrown {
+    ///returns the reference point out of the given list that is closest to p
+    pub fn closest(references: &[(Point, f64)], p: Point) -> (Point, f64) {
+        *references.iter()
+            .min_by(|a, b| a.0.distance_sq(p).partial_cmp(&b.0.distance_sq(p)).unwrap())
+            .unwrap()
+    }
+
+    #[inline]
+    fn term_at(k: usize, p: Point) -> f64 {
+        let dx = p.x - X0[k];
+        let dy = p.y - Y0[k];
+        A[k] * (ALPHA[k] * dx.powi(2) + BETA[k] * dx * dy + GAMMA[k] * dy.powi(2)).exp()
+    }
+}
+
+impl PotentialEnergySurface for MullerBrown {
+    fn energy_at(&self, p: Point) -> f64 {
+        (0..4).map(|k| Self::term_at(k, p)).sum()
+    }
+
+    fn gradient_at(&self, p: Point) -> Point {
+        (0..4).map(|k| {
+            let dx = p.x - X0[k];
+            let dy = p.y - Y0[k];
+            let value = Self::term_at(k, p);
+            // negative, because we want the arrows to point downhill
+            Point {
+                x: -value * (2.0 * ALPHA[k] * dx + BETA[k] * dy),
+                y: -value * (BETA[k] * dx + 2.0 * GAMMA[k] * dy),
+            }
+        }).sum()
+    }
+
+    fn hessian_at(&self, p: Point) -> Option<Hessian> {
+        Some((0..4).map(|k| {
+            let dx = p.x - X0[k];
+            let dy = p.y - Y0[k];
+            let value = Self::term_at(k, p);
+            // derivatives of the exponent
+            let qx = 2.0 * ALPHA[k] * dx + BETA[k] * dy;
+            let qy = BETA[k] * dx + 2.0 * GAMMA[k] * dy;
+            Hessian {
+                xx: value * (qx * qx + 2.0 * ALPHA[k]),
+                xy: value * (qx * qy + BETA[k]),
+                yy: value * (qy * qy + 2.0 * GAMMA[k]),
+            }
+        }).sum())
+    }
+}
@@ -0,0 +1,345 @@
+use std::fs;
+use crate::point::Point;
+use crate::pes::PotentialEnergySurface;
+use crate::chain::{Chain, ChainConfig};
+use crate::image::Image;
+use crate::minima::Minima;
+use crate::convergence::Criterion;
+use crate::relaxation::{Relaxation, LoopState};
+
+///which minima are connected and which route is searched
+#[derive(Debug, Copy, Clone, Default, Deserialize, Serialize)]
+pub struct NetworkConfig {
+    ///minima further apart than this are never connected directly
+    #[serde(default)]
+    pub(crate) max_distance: Option<f64>,
+    ///the two minima (by index, lowest energy first) to find the route with the lowest barrier between.
+    /// If left out the routes from the lowest minimum to all others are reported
+    #[serde(default)]
+    pub(crate) route: Option<[usize; 2]>,
+}
+
+///how many iterations a chain between two minima gets at most if max_iterations is null
+const PAIR_ITERATIONS: usize = 1000;
+
+///a minimum of the network
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct Node {
+    pub(crate) position: Point,
+    pub(crate) energy: f64,
+}
+
+///the minimum energy path between two neighboring minima
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Connection {
+    pub(crate) from: usize,
+    pub(crate) to: usize,
+    ///the highest point of the path
+    pub(crate) saddle: Point,
+    pub(crate) energy: f64,
+    pub(crate) forward_barrier: f64,
+    pub(crate) reverse_barrier: f64,
+    pub(crate) path: Vec<Point>,
+}
+
+///a route through the network, the highest saddle point on the way is as low as possible
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Route {
+    pub(crate) nodes: Vec<usize>,
+    ///indices of the connections used, in order
+    pub(crate) connections: Vec<usize>,
+    ///the energy of the highest saddle point along the route
+    pub(crate) highest: f64,
+}
+
+///minima as nodes and the saddle points between them as edges
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Network {
+    pub(crate) nodes: Vec<Node>,
+    pub(crate) connections: Vec<Connection>,
+}
+
+impl Network {
+    ///pairs of minima that are neighbors: no third minimum lies within the circle that has the pair
+    /// as its diameter
+    pub fn neighbors(minima: &Minima, config: &NetworkConfig) -> Vec<(usize, usize)> {
+        let positions: Vec<Point> = minima.minima.iter().map(|m| m.position).collect();
+        let mut pairs = Vec::new();
+        for i in 0..positions.len() {
+            for j in i + 1..positions.len() {
+                let (a, b) = (positions[i], positions[j]);
+                if let Some(max_distance) = config.max_distance {
+                    if a.distance_sq(b) > max_distance.powi(2) {
+                        continue;
+                    }
+                }
+                let center = 0.5 * (a + b);
+                let radius_sq = 0.25 * a.distance_sq(b);
+                let blocked = positions.iter().enumerate()
+                    .any(|(k, p)| k != i && k != j && p.distance_sq(center) < radius_sq);
+                if !blocked {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    pub fn new(minima: &Minima) -> Self {
+        Network {
+            nodes: minima.minima.iter().map(|m| Node { position: m.position, energy: m.energy }).collect(),
+            connections: Vec::new(),
+        }
+    }
+
+    ///runs a chain between the two minima and adds the connection. Fails if the chain stays unstable,
+    /// doesn't converge within max_iterations (PAIR_ITERATIONS if there is no limit), passes through
+    /// another minimum on the way or finds no barrier
+    pub fn connect(&mut self, pes: &dyn PotentialEnergySurface, from: usize, to: usize,
+                   chain_config: &ChainConfig, relaxation: &Relaxation) -> Result<&Connection, String> {
+        let mut config = chain_config.clone();
+        config.start = self.nodes[from].position;
+        config.end = self.nodes[to].position;
+        config.relax_ends = false;
+        config.waypoints.clear();
+        config.path_file = None;
+        let mut convergence = *relaxation.convergence;
+        convergence.max_iterations = convergence.max_iterations.or(Some(PAIR_ITERATIONS));
+        let relaxation = Relaxation { convergence: &convergence, ..*relaxation };
+        let mut chain = Chain::new(config);
+        let mut state = LoopState::new(relaxation.stability.allowed_energy_rise(&chain, pes));
+        match relaxation.run(&mut chain, pes, &mut state, None) {
+            Ok(Criterion::MaxIterations) =>
+                return Err(format!("the chain didn't converge within {} iterations", chain.iteration)),
+            Ok(_) => {}
+            Err(problem) => return Err(format!("the chain stays unstable: {}", problem)),
+        }
+
+        let energies: Vec<f64> = chain.elements.iter().map(|&p| pes.energy_at(p)).collect();
+        // every dip along the path has to belong to one of the two minima
+        for k in 1..energies.len() - 1 {
+            if energies[k] < energies[k - 1] && energies[k] < energies[k + 1] {
+                let mut p = chain.elements[k];
+                p.move_to_minimum(pes, chain.config.optimizer, &chain.config.minimizer, relaxation.convergence_limit);
+                let closest = self.closest(p);
+                if closest != from && closest != to {
+                    return Err(format!("the path passes through minimum {}", closest));
+                }
+            }
+        }
+        let i = chain.saddle_image(pes);
+        let energy = energies[i];
+        if energy <= self.nodes[from].energy.max(self.nodes[to].energy) {
+            return Err("there is no barrier between them".to_string());
+        }
+        self.connections.push(Connection {
+            from,
+            to,
+            saddle: chain.elements[i],
+            energy,
+            forward_barrier: energy - self.nodes[from].energy,
+            reverse_barrier: energy - self.nodes[to].energy,
+            path: chain.elements,
+        });
+        Ok(&self.connections[self.connections.len() - 1])
+    }
+
+    ///index of the node closest to p
+    fn closest(&self, p: Point) -> usize {
+        (0..self.nodes.len())
+            .min_by(|&a, &b| self.nodes[a].position.distance_sq(p).partial_cmp(&self.nodes[b].position.distance_sq(p)).unwrap())
+            .unwrap()
+    }
+
+    ///the route between the two minima whose highest saddle point is lowest, a minimax version of
+    /// Dijkstra's algorithm. None if they are not connected
+    pub fn route(&self, from: usize, to: usize) -> Option<Route> {
+        let n = self.nodes.len();
+        if from >= n || to >= n {
+            return None;
+        }
+        // the lowest possible highest energy on the way to each node, and how we got there
+        let mut highest = vec![f64::INFINITY; n];
+        let mut previous: Vec<Option<usize>> = vec![None; n];
+        let mut done = vec![false; n];
+        highest[from] = self.nodes[from].energy;
+        loop {
+            let current = (0..n).filter(|&i| !done[i] && highest[i].is_finite())
+                .min_by(|&a, &b| highest[a].partial_cmp(&highest[b]).unwrap())?;
+            if current == to {
+                break;
+            }
+            done[current] = true;
+            for (c, connection) in self.connections.iter().enumerate() {
+                let next = if connection.from == current {
+                    connection.to
+                } else if connection.to == current {
+                    connection.from
+                } else {
+                    continue;
+                };
+                let over = highest[current].max(connection.energy);
+                if !done[next] && over < highest[next] {
+                    highest[next] = over;
+                    previous[next] = Some(c);
+                }
+            }
+        }
+
+        let mut nodes = vec![to];
+        let mut connections = Vec::new();
+        let mut current = to;
+        while let Some(c) = previous[current] {
+            let connection = &self.connections[c];
+            current = if connection.from == current { connection.to } else { connection.from };
+            nodes.push(current);
+            connections.push(c);
+        }
+        nodes.reverse();
+        connections.reverse();
+        Some(Route { nodes, connections, highest: highest[to] })
+    }
+
+    pub fn save_json(&self, file: &str) -> std::io::Result<()> {
+        fs::write(file, serde_json::ser::to_string_pretty(self).unwrap())
+    }
+
+    ///the network for Graphviz. The connections of the route, if given, are drawn thick and red
+    pub fn save_dot(&self, file: &str, route: Option<&Route>) -> std::io::Result<()> {
+        let mut data = String::from("graph network {\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            data += &format!("    {} [label=\"{}\\nE = {:.4}\"];\n", i, i, node.energy);
+        }
+        for (c, connection) in self.connections.iter().enumerate() {
+            let style = match route {
+                Some(route) if route.connections.contains(&c) => ", color=red, penwidth=2",
+                _ => "",
+            };
+            data += &format!("    {} -- {} [label=\"{:.4}\"{}];\n", connection.from, connection.to, connection.energy, style);
+        }
+        data += "}\n";
+        fs::write(file, data)
+    }
+
+    ///draws all paths over the PES, the minima in black and the saddle points in blue
+    pub fn paint(&self, file: &str, img: &Image, point_size: f64) {
+        let mut buffer = img.canvas();
+        for connection in &self.connections {
+            for pair in connection.path.windows(2) {
+                img.draw_line(&mut buffer, pair[0], pair[1], &[0, 255u8, 0]);
+            }
+            img.draw_circle(&mut buffer, connection.saddle, point_size, &[0, 0, 255u8]);
+        }
+        for node in &self.nodes {
+            img.draw_circle(&mut buffer, node.position, 2.0 * point_size, &[0, 0, 0]);
+        }
+        buffer.save(file).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::Method;
+    use crate::convergence::ConvergenceConfig;
+    use crate::stability::StabilityConfig;
+    use crate::image::ImageConfig;
+    use crate::optimizer::OptimizerConfig;
+    use crate::minimizer::MinimizerConfig;
+    use crate::minima::Minimum;
+    use crate::muller_brown::{MullerBrown, MINIMA, SADDLES};
+
+    fn muller_brown_minima() -> Minima {
+        Minima {
+            minima: MINIMA.iter().map(|&(position, energy)| Minimum { position, energy, basin: vec![] }).collect(),
+            discarded: 0,
+        }
+    }
+
+    ///a connection that only has the energy of its saddle point
+    fn connection(from: usize, to: usize, energy: f64) -> Connection {
+        Connection {
+            from,
+            to,
+            saddle: Point { x: 0.0, y: 0.0 },
+            energy,
+            forward_barrier: 0.0,
+            reverse_barrier: 0.0,
+            path: vec![],
+        }
+    }
+
+    #[test]
+    fn only_the_muller_brown_neighbors_are_paired() {
+        // the shallow minimum lies between the other two
+        assert_eq!(Network::neighbors(&muller_brown_minima(), &NetworkConfig::default()), vec![(0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn route_avoids_the_highest_saddle() {
+        let mut network = Network::new(&muller_brown_minima());
+        network.connections = vec![connection(0, 2, SADDLES[0].1), connection(2, 1, SADDLES[1].1), connection(0, 1, 0.0)];
+        let route = network.route(0, 1).unwrap();
+        assert_eq!(route.nodes, vec![0, 2, 1]);
+        assert_eq!(route.connections, vec![0, 1]);
+        assert!((route.highest - SADDLES[0].1).abs() < 1e-12);
+
+        // a direct connection lower than the detour wins
+        network.connections[2].energy = -50.0;
+        let route = network.route(0, 1).unwrap();
+        assert_eq!(route.nodes, vec![0, 1]);
+        assert!((route.highest + 50.0).abs() < 1e-12);
+
+        network.connections.clear();
+        assert!(network.route(0, 1).is_none());
+    }
+
+    #[test]
+    fn connects_the_muller_brown_minima_over_their_saddles() {
+        let chain_config = ChainConfig {
+            spring_constant: 200.0,
+            pin_ends: true,
+            free_ends: false,
+            relax_ends: false,
+            start: MINIMA[0].0,
+            end: MINIMA[1].0,
+            elements: 20,
+            method: Method::Neb,
+            climbing_image: Some(0),
+            growth_interval: 10,
+            optimizer: OptimizerConfig::Lbfgs { memory: 10, max_step: 0.02 },
+            minimizer: MinimizerConfig::default(),
+            waypoints: vec![],
+            path_file: None,
+            spline: false,
+        };
+        let convergence = ConvergenceConfig { energy: false, max_force: Some(1e-4), ..ConvergenceConfig::default() };
+        let domain = ImageConfig {
+            contour_lines: 20.0,
+            x0: -1.5,
+            y0: -0.5,
+            width: 2.7,
+            height: 2.5,
+            resolution_x: 270,
+            resolution_y: 250,
+            point_size: 0.01,
+            line_width: 0.002,
+        };
+        let relaxation = Relaxation {
+            convergence: &convergence,
+            convergence_limit: 1e-9,
+            stability: &StabilityConfig::default(),
+            domain: &domain,
+        };
+
+        let mut network = Network::new(&muller_brown_minima());
+        for &(from, to, saddle) in [(0, 2, SADDLES[0]), (1, 2, SADDLES[1])].iter() {
+            let connection = network.connect(&MullerBrown, from, to, &chain_config, &relaxation).unwrap();
+            assert!(connection.saddle.distance_sq(saddle.0).sqrt() < 1e-4, "saddle at ({}|{})", connection.saddle.x, connection.saddle.y);
+            assert!((connection.energy - saddle.1).abs() < 1e-4);
+        }
+        let route = network.route(0, 1).unwrap();
+        assert_eq!(route.nodes, vec![0, 2, 1]);
+        assert!((route.highest - SADDLES[0].1).abs() < 1e-4);
+    }
+}
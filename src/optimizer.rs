@@ -0,0 +1,291 @@
+use crate::point::Point;
+
+///how the elements are moved once the forces on them are known
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OptimizerConfig {
+    ///move every element by step_size times the force acting on it
+    SteepestDescent {
+        step_size: f64,
+    },
+    ///velocity Verlet dynamics where only the velocity along the force is kept, and none at all as soon
+    /// as the band moves uphill
+    QuickMin {
+        time_step: f64,
+    },
+    ///fast inertial relaxation engine, velocity Verlet dynamics with an adaptive time step that mixes the
+    /// velocity towards the force. See Bitzek et al., Phys. Rev. Lett. 97, 170201 (2006)
+    Fire {
+        time_step: f64,
+        max_time_step: f64,
+        #[serde(default = "default_fire_min_steps")]
+        min_steps: usize,
+        #[serde(default = "default_fire_increase")]
+        increase: f64,
+        #[serde(default = "default_fire_decrease")]
+        decrease: f64,
+        #[serde(default = "default_fire_alpha")]
+        alpha: f64,
+        #[serde(default = "default_fire_alpha_decrease")]
+        alpha_decrease: f64,
+    },
+    ///limited memory BFGS on all coordinates of the band at once, with the forces as the negative
+    /// gradient. No element moves further than max_step in one iteration
+    Lbfgs {
+        memory: usize,
+        max_step: f64,
+    },
+}
+
+fn default_fire_min_steps() -> usize {
+    5
+}
+
+fn default_fire_increase() -> f64 {
+    1.1
+}
+
+fn default_fire_decrease() -> f64 {
+    0.5
+}
+
+fn default_fire_alpha() -> f64 {
+    0.1
+}
+
+fn default_fire_alpha_decrease() -> f64 {
+    0.99
+}
+
+impl Default for OptimizerConfig {
+    ///a plain gradient descent that moves by exactly the force, like the program always did
+    fn default() -> Self {
+        OptimizerConfig::SteepestDescent { step_size: 1.0 }
+    }
+}
+
+///an optimizer together with the state it keeps between iterations
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Optimizer {
+    pub(crate) config: OptimizerConfig,
+    pub(crate) velocities: Vec<Point>,
+    ///the current time step of the dynamics based optimizers, adapted by FIRE
+    pub(crate) time_step: f64,
+    ///FIRE: how strongly the velocity is turned towards the force
+    pub(crate) alpha: f64,
+    ///FIRE: how many steps went by since the last one uphill
+    pub(crate) downhill_steps: usize,
+    ///L-BFGS: the last position changes and the matching changes of the forces, oldest first
+    pub(crate) history: Vec<(Vec<Point>, Vec<Point>)>,
+    ///L-BFGS: the positions and forces of the last step
+    pub(crate) last_positions: Vec<Point>,
+    pub(crate) last_forces: Vec<Point>,
+}
+
+impl Optimizer {
+    pub fn new(config: OptimizerConfig) -> Self {
+        let (time_step, alpha) = match config {
+            OptimizerConfig::SteepestDescent { .. } | OptimizerConfig::Lbfgs { .. } => (0.0, 0.0),
+            OptimizerConfig::QuickMin { time_step } => (time_step, 0.0),
+            OptimizerConfig::Fire { time_step, alpha, .. } => (time_step, alpha),
+        };
+        Optimizer {
+            config,
+            velocities: Vec::new(),
+            time_step,
+            alpha,
+            downhill_steps: 0,
+            history: Vec::new(),
+            last_positions: Vec::new(),
+            last_forces: Vec::new(),
+        }
+    }
+
+    ///makes all further steps smaller by the given factor, used after the simulation became unstable
+    pub fn reduce_step(&mut self, factor: f64) {
+        match &mut self.config {
+            OptimizerConfig::SteepestDescent { step_size } => *step_size *= factor,
+            OptimizerConfig::QuickMin { time_step } => *time_step *= factor,
+            OptimizerConfig::Fire { time_step, max_time_step, .. } => {
+                *time_step *= factor;
+                *max_time_step *= factor;
+            }
+            OptimizerConfig::Lbfgs { max_step, .. } => *max_step *= factor,
+        }
+        self.time_step *= factor;
+        for v in self.velocities.iter_mut() {
+            *v = Point { x: 0.0, y: 0.0 };
+        }
+        self.history.clear();
+    }
+
+    ///moves the positions according to the forces acting on them
+    pub fn step(&mut self, positions: &mut [Point], forces: &[Point]) {
+        let dt = match self.config {
+            OptimizerConfig::SteepestDescent { step_size } => {
+                for (p, &force) in positions.iter_mut().zip(forces) {
+                    *p += step_size * force;
+                }
+                return;
+            }
+            OptimizerConfig::Lbfgs { memory, max_step } => {
+                self.lbfgs_step(positions, forces, memory, max_step);
+                return;
+            }
+            OptimizerConfig::QuickMin { .. } | OptimizerConfig::Fire { .. } => self.time_step,
+        };
+
+        // the number of elements can change (growing string), old velocities are meaningless then
+        if self.velocities.len() != positions.len() {
+            self.velocities = vec![Point { x: 0.0, y: 0.0 }; positions.len()];
+        } else {
+            // velocity Verlet: finish the last step with the second half kick from the new forces
+            for (v, &force) in self.velocities.iter_mut().zip(forces) {
+                *v += 0.5 * dt * force;
+            }
+        }
+
+        let power: f64 = self.velocities.iter().zip(forces).map(|(v, f)| v.dot_product(*f)).sum();
+        let force_sq: f64 = forces.iter().map(|f| f.dot_product(*f)).sum();
+        match self.config {
+            OptimizerConfig::QuickMin { .. } => {
+                // keep only the velocity along the force, and none if we are going uphill
+                let scale = if power > 0.0 && force_sq > 0.0 { power / force_sq } else { 0.0 };
+                for (v, &force) in self.velocities.iter_mut().zip(forces) {
+                    *v = scale * force;
+                }
+            }
+            OptimizerConfig::Fire { max_time_step, min_steps, increase, decrease, alpha, alpha_decrease, .. } => {
+                if power > 0.0 {
+                    // turn the velocity towards the force
+                    let velocity_norm = self.velocities.iter().map(|v| v.dot_product(*v)).sum::<f64>().sqrt();
+                    let force_norm = force_sq.sqrt();
+                    for (v, &force) in self.velocities.iter_mut().zip(forces) {
+                        *v = (1.0 - self.alpha) * *v + self.alpha * velocity_norm / force_norm * force;
+                    }
+                    self.downhill_steps += 1;
+                    if self.downhill_steps > min_steps {
+                        self.time_step = (self.time_step * increase).min(max_time_step);
+                        self.alpha *= alpha_decrease;
+                    }
+                } else {
+                    // we went uphill, stop and start carefully again
+                    for v in self.velocities.iter_mut() {
+                        *v = Point { x: 0.0, y: 0.0 };
+                    }
+                    self.time_step *= decrease;
+                    self.alpha = alpha;
+                    self.downhill_steps = 0;
+                }
+            }
+            OptimizerConfig::SteepestDescent { .. } | OptimizerConfig::Lbfgs { .. } => unreachable!(),
+        }
+
+        // first half kick with the current forces and the drift of the positions
+        let dt = self.time_step;
+        for ((p, v), &force) in positions.iter_mut().zip(self.velocities.iter_mut()).zip(forces) {
+            *v += 0.5 * dt * force;
+            *p += dt * *v;
+        }
+    }
+
+    fn lbfgs_step(&mut self, positions: &mut [Point], forces: &[Point], memory: usize, max_step: f64) {
+        // remember how the forces changed with the last step. The number of elements can change
+        // (growing string), then the history is meaningless
+        if self.last_positions.len() == positions.len() {
+            let s: Vec<Point> = positions.iter().zip(&self.last_positions).map(|(&p, &last)| p - last).collect();
+            // the gradient is the negative force
+            let y: Vec<Point> = forces.iter().zip(&self.last_forces).map(|(&f, &last)| last - f).collect();
+            if dot(&s, &y) > 0.0 {
+                self.history.push((s, y));
+                if self.history.len() > memory {
+                    self.history.remove(0);
+                }
+            } else {
+                // the curvature is not positive, so the collected information is not helping
+                self.history.clear();
+            }
+        } else {
+            self.history.clear();
+        }
+
+        // two loop recursion to get the step -H*gradient = H*force
+        let mut q = forces.to_vec();
+        let mut alphas = Vec::with_capacity(self.history.len());
+        for (s, y) in self.history.iter().rev() {
+            let alpha = dot(s, &q) / dot(y, s);
+            for (q, &y) in q.iter_mut().zip(y) {
+                *q += -alpha * y;
+            }
+            alphas.push(alpha);
+        }
+        if let Some((s, y)) = self.history.last() {
+            let gamma = dot(s, y) / dot(y, y);
+            for q in q.iter_mut() {
+                *q = gamma * *q;
+            }
+        }
+        for ((s, y), alpha) in self.history.iter().zip(alphas.into_iter().rev()) {
+            let beta = dot(y, &q) / dot(y, s);
+            for (q, &s) in q.iter_mut().zip(s) {
+                *q += (alpha - beta) * s;
+            }
+        }
+        let mut step = q;
+
+        // only ever go downhill, otherwise start over with a steepest descent step
+        if dot(&step, forces) <= 0.0 {
+            self.history.clear();
+            step = forces.to_vec();
+        }
+
+        // make sure the band can't jump across the surface
+        let longest = step.iter().map(|s| s.norm()).fold(0.0, f64::max);
+        let scale = if longest > max_step { max_step / longest } else { 1.0 };
+
+        self.last_positions = positions.to_vec();
+        self.last_forces = forces.to_vec();
+        for (p, &s) in positions.iter_mut().zip(&step) {
+            *p += scale * s;
+        }
+    }
+}
+
+///dot product of two vectors made up of all coordinates of the band
+fn dot(a: &[Point], b: &[Point]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a.dot_product(*b)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pes::PotentialEnergySurface;
+    use crate::muller_brown::{MullerBrown, MINIMA};
+
+    ///how many steps the optimizer needs to bring a point from start below a force of 1e-6
+    fn steps_to_minimum(config: OptimizerConfig, start: Point) -> (usize, Point) {
+        let mut optimizer = Optimizer::new(config);
+        let mut positions = [start];
+        for steps in 0..10_000 {
+            let forces = [MullerBrown.gradient_at(positions[0])];
+            if forces[0].norm() < 1e-6 {
+                return (steps, positions[0]);
+            }
+            optimizer.step(&mut positions, &forces);
+        }
+        (10_000, positions[0])
+    }
+
+    #[test]
+    fn lbfgs_finds_the_muller_brown_minima() {
+        let offset = Point { x: 0.05, y: -0.05 };
+        for &(minimum, _) in MINIMA.iter() {
+            let (steps, p) = steps_to_minimum(OptimizerConfig::Lbfgs { memory: 5, max_step: 0.02 }, minimum + offset);
+            assert!(p.distance_sq(minimum).sqrt() < 1e-5, "ended at ({}|{})", p.x, p.y);
+
+            // the curvature it collects makes it much faster than plain steepest descent
+            let (descent_steps, _) = steps_to_minimum(OptimizerConfig::SteepestDescent { step_size: 1e-4 }, minimum + offset);
+            assert!(steps < descent_steps / 5, "{} L-BFGS and {} steepest descent steps", steps, descent_steps);
+        }
+    }
+}
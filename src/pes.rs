@@ -1,4 +1,69 @@
+use std::iter::Sum;
+use std::ops::Add;
 use crate::point::Point;
+use crate::muller_brown::MullerBrown;
+
+/// Anything that can be used as the landscape for the MEP search. The algorithms in this program
+/// only ever ask for the energy and the downhill direction at a point, so any analytic potential
+/// implementing this trait can be plugged in instead of the sum of gaussians below.
+pub trait PotentialEnergySurface {
+    /// the energy at the given point
+    fn energy_at(&self, p: Point) -> f64;
+
+    /// the negative gradient at the given point.
+    /// negative, because we want the arrows to point downhill
+    fn gradient_at(&self, p: Point) -> Point;
+
+    /// the matrix of second derivatives of the energy at the given point, if the surface knows it
+    fn hessian_at(&self, _p: Point) -> Option<Hessian> {
+        None
+    }
+}
+
+/// the second derivatives of the energy. The matrix is symmetric, so xy is also yx
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct Hessian {
+    pub(crate) xx: f64,
+    pub(crate) xy: f64,
+    pub(crate) yy: f64,
+}
+
+impl Hessian {
+    pub fn scaled(&self, factor: f64) -> Self {
+        Hessian { xx: factor * self.xx, xy: factor * self.xy, yy: factor * self.yy }
+    }
+
+    ///the matrix applied to the vector p
+    pub fn times(&self, p: Point) -> Point {
+        Point { x: self.xx * p.x + self.xy * p.y, y: self.xy * p.x + self.yy * p.y }
+    }
+
+    ///the eigenvalues in ascending order together with their normed eigenvectors
+    pub fn eigen(&self) -> ([f64; 2], [Point; 2]) {
+        let mean = 0.5 * (self.xx + self.yy);
+        let radius = (0.25 * (self.xx - self.yy).powi(2) + self.xy.powi(2)).sqrt();
+        let values = [mean - radius, mean + radius];
+        // the angle of the eigenvector of the larger eigenvalue, the other one is perpendicular to it
+        let angle = 0.5 * (2.0 * self.xy).atan2(self.xx - self.yy);
+        let upper = Point { x: angle.cos(), y: angle.sin() };
+        let lower = Point { x: -upper.y, y: upper.x };
+        (values, [lower, upper])
+    }
+}
+
+impl Add for Hessian {
+    type Output = Hessian;
+
+    fn add(self, other: Self) -> Self::Output {
+        Hessian { xx: self.xx + other.xx, xy: self.xy + other.xy, yy: self.yy + other.yy }
+    }
+}
+
+impl Sum for Hessian {
+    fn sum<I: Iterator<Item=Self>>(iter: I) -> Self {
+        iter.fold(Hessian { xx: 0.0, xy: 0.0, yy: 0.0 }, |a, b| a + b)
+    }
+}
 
 #[derive(Debug, Copy, Clone, Deserialize, Serialize)]
 pub struct Gaussian {
@@ -7,13 +72,28 @@ pub struct Gaussian {
     pub(crate) sig_x: f64,
     pub(crate) y0: f64,
     pub(crate) sig_y: f64,
+    ///counter clockwise rotation of the sig_x and sig_y axes in degrees
+    #[serde(default)]
+    pub(crate) angle: f64,
 }
 
 impl Gaussian {
+    ///the offset of p from the center, expressed along the (rotated) axes of this gaussian
+    #[inline]
+    fn local_offset(&self, p: Point) -> Point {
+        let offset = Point { x: p.x - self.x0, y: p.y - self.y0 };
+        if self.angle == 0.0 {
+            offset
+        } else {
+            offset.rotate(-self.angle.to_radians())
+        }
+    }
+
     #[inline]
     fn value_at(&self, p: Point) -> f64 {
-        let exponent_x = (p.x - self.x0).powi(2) / (2.0 * self.sig_x.powi(2));
-        let exponent_y = (p.y - self.y0).powi(2) / (2.0 * self.sig_y.powi(2));
+        let offset = self.local_offset(p);
+        let exponent_x = offset.x.powi(2) / (2.0 * self.sig_x.powi(2));
+        let exponent_y = offset.y.powi(2) / (2.0 * self.sig_y.powi(2));
         self.a * (-(exponent_x + exponent_y)).exp()
     }
 
@@ -21,26 +101,85 @@ impl Gaussian {
     /// negative, because we want the arrows to point downhill
     #[inline]
     fn gradient_at(&self, p: Point) -> Point {
-        let dx = self.value_at(p) * (p.x - self.x0) / (2.0 * self.sig_x.powi(2));
-        let dy = self.value_at(p) * (p.y - self.y0) / (2.0 * self.sig_y.powi(2));
-        Point { x: dx, y: dy }
+        let offset = self.local_offset(p);
+        let value = self.value_at(p);
+        let dx = value * offset.x / self.sig_x.powi(2);
+        let dy = value * offset.y / self.sig_y.powi(2);
+        let gradient = Point { x: dx, y: dy };
+        // turn the gradient back from the axes of the gaussian into the axes of the PES
+        if self.angle == 0.0 {
+            gradient
+        } else {
+            gradient.rotate(self.angle.to_radians())
+        }
     }
+
+    #[inline]
+    fn hessian_at(&self, p: Point) -> Hessian {
+        let offset = self.local_offset(p);
+        let value = self.value_at(p);
+        let var_x = self.sig_x.powi(2);
+        let var_y = self.sig_y.powi(2);
+        let uu = value * (offset.x.powi(2) / var_x - 1.0) / var_x;
+        let vv = value * (offset.y.powi(2) / var_y - 1.0) / var_y;
+        let uv = value * offset.x * offset.y / (var_x * var_y);
+        if self.angle == 0.0 {
+            return Hessian { xx: uu, xy: uv, yy: vv };
+        }
+        // turn the hessian back from the axes of the gaussian into the axes of the PES
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        Hessian {
+            xx: cos * cos * uu - 2.0 * cos * sin * uv + sin * sin * vv,
+            xy: cos * sin * (uu - vv) + (cos * cos - sin * sin) * uv,
+            yy: sin * sin * uu + 2.0 * cos * sin * uv + cos * cos * vv,
+        }
+    }
+}
+
+///which analytic surface the gaussians are added onto
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Model {
+    #[default]
+    Gaussians,
+    MullerBrown,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PES {
     pub(crate) scale: f64,
+    #[serde(default)]
+    pub(crate) model: Model,
+    #[serde(default)]
     pub(crate) gaussians: Vec<Gaussian>,
 }
 
-impl PES {
-    pub(crate) fn energy_at(&self, p: Point) -> f64 {
+impl PotentialEnergySurface for PES {
+    fn energy_at(&self, p: Point) -> f64 {
+        let base = match self.model {
+            Model::Gaussians => 0.0,
+            Model::MullerBrown => MullerBrown.energy_at(p),
+        };
         let energy: f64 = self.gaussians.iter().map(|g| g.value_at(p)).sum();
-        self.scale * energy
+        self.scale * (base + energy)
     }
 
-    pub fn gradient_at(&self, p: Point) -> Point {
+    fn gradient_at(&self, p: Point) -> Point {
+        let base = match self.model {
+            Model::Gaussians => Point { x: 0.0, y: 0.0 },
+            Model::MullerBrown => MullerBrown.gradient_at(p),
+        };
         let gradient: Point = self.gaussians.iter().map(|g| g.gradient_at(p)).sum();
-        self.scale * gradient
+        self.scale * (base + gradient)
+    }
+
+    fn hessian_at(&self, p: Point) -> Option<Hessian> {
+        let base = match self.model {
+            Model::Gaussians => Hessian { xx: 0.0, xy: 0.0, yy: 0.0 },
+            Model::MullerBrown => MullerBrown.hessian_at(p)?,
+        };
+        let hessian: Hessian = self.gaussians.iter().map(|g| g.hessian_at(p)).sum();
+        Some((base + hessian).scaled(self.scale))
     }
 }
\ No newline at end of file
@@ -1,7 +1,9 @@
 use std::iter::Sum;
 use std::ops::{Add, AddAssign, Sub, Mul, Div};
 use std::f64::consts::FRAC_PI_2;
-use crate::pes::PES;
+use crate::pes::PotentialEnergySurface;
+use crate::optimizer::OptimizerConfig;
+use crate::minimizer::{self, MinimizerConfig, Minimization};
 
 
 #[derive(Debug, Copy, Clone, Deserialize, Serialize)]
@@ -11,28 +13,20 @@ pub struct Point {
 }
 
 impl Point {
-    pub fn move_to_minimum(&mut self, pes: &PES, convergence_limit: f64) {
-        let mut last_energy: f64;
-        let mut energy = pes.energy_at(*self);
-        loop {
-            // move point towards minimum
-            *self += pes.gradient_at(*self);
-
-            // update energy values
-            last_energy = energy;
-            energy = pes.energy_at(*self);
-
-            // stop iterating if we can't change anything anymore
-            if last_energy - energy < convergence_limit {
-                break;
-            }
-        }
+    ///relaxes the point into the closest minimum, see minimizer::minimize
+    pub fn move_to_minimum(&mut self, pes: &dyn PotentialEnergySurface, optimizer: OptimizerConfig,
+                           minimizer: &MinimizerConfig, convergence_limit: f64) -> Minimization {
+        minimizer::minimize(self, pes, optimizer, minimizer, convergence_limit)
     }
 
     pub fn distance_sq(&self, other: Point) -> f64 {
         (self.x - other.x).powi(2) + (self.y - other.y).powi(2)
     }
 
+    pub fn norm(&self) -> f64 {
+        (self.x.powi(2) + self.y.powi(2)).sqrt()
+    }
+
     pub fn rotate(&self, rad: f64) -> Self {
         Point {
             x: self.x * rad.cos() - self.y * rad.sin(),
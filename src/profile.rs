@@ -0,0 +1,284 @@
+use std::fs;
+use crate::point::Point;
+use crate::chain::{Chain, arc_lengths};
+use crate::pes::PotentialEnergySurface;
+use crate::image::{Image, PLOT_POINT_SIZE};
+
+///one element of the chain, seen as a point on the energy profile
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct ProfilePoint {
+    pub(crate) position: Point,
+    ///distance along the path from the start, the reaction coordinate
+    pub(crate) arc_length: f64,
+    pub(crate) energy: f64,
+    ///length of the part of the force perpendicular to the path
+    pub(crate) perpendicular_force: f64,
+    ///the force along the tangent, the negative slope of the profile
+    pub(crate) parallel_force: f64,
+    pub(crate) tangent: Point,
+}
+
+///the highest point of the smooth interpolated profile
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct InterpolatedMaximum {
+    pub(crate) position: Point,
+    pub(crate) arc_length: f64,
+    pub(crate) energy: f64,
+}
+
+///the energy along the path, together with the barrier
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Profile {
+    pub(crate) points: Vec<ProfilePoint>,
+    ///index of the highest point, not counting the ends
+    pub(crate) highest: usize,
+    ///energy difference between the highest point and the start
+    pub(crate) forward_barrier: f64,
+    ///energy difference between the highest point and the end
+    pub(crate) reverse_barrier: f64,
+    ///where the profile interpolated between the elements is highest
+    pub(crate) interpolated_maximum: InterpolatedMaximum,
+}
+
+impl Profile {
+    pub fn new(chain: &Chain, pes: &dyn PotentialEnergySurface) -> Self {
+        let lengths = arc_lengths(&chain.elements);
+        let tangents = chain.tangents(pes);
+        let points: Vec<ProfilePoint> = chain.elements.iter().enumerate().map(|(i, &position)| {
+            let gradient = pes.gradient_at(position);
+            let tangent = tangents[i];
+            ProfilePoint {
+                position,
+                arc_length: lengths[i],
+                energy: pes.energy_at(position),
+                perpendicular_force: (gradient - gradient.dot_product(tangent) * tangent).norm(),
+                parallel_force: gradient.dot_product(tangent),
+                tangent,
+            }
+        }).collect();
+        let highest = chain.highest_image(pes);
+        let top = points[highest].energy;
+        Profile {
+            forward_barrier: top - points[0].energy,
+            reverse_barrier: top - points[points.len() - 1].energy,
+            highest,
+            interpolated_maximum: interpolated_maximum(&points),
+            points,
+        }
+    }
+
+    ///the smooth profile as (arc length, energy) pairs, with the given number of samples between
+    /// each pair of neighboring elements
+    pub fn interpolated(&self, samples: usize) -> Vec<(f64, f64)> {
+        let mut curve = Vec::with_capacity((self.points.len() - 1) * samples + 1);
+        for pair in self.points.windows(2) {
+            let cubic = Cubic::between(pair[0], pair[1]);
+            for j in 0..samples {
+                let t = j as f64 / samples as f64;
+                curve.push((pair[0].arc_length + t * cubic.length, cubic.at(t)));
+            }
+        }
+        let last = self.points[self.points.len() - 1];
+        curve.push((last.arc_length, last.energy));
+        curve
+    }
+
+    pub fn save_interpolated_csv(&self, file: &str) -> std::io::Result<()> {
+        let mut data = String::from("arc_length,energy\n");
+        for (s, e) in self.interpolated(10) {
+            data += &format!("{},{}\n", s, e);
+        }
+        fs::write(file, data)
+    }
+
+    pub fn save_csv(&self, file: &str) -> std::io::Result<()> {
+        let mut data = String::from("index,arc_length,x,y,energy,perpendicular_force,tangent_x,tangent_y\n");
+        for (i, p) in self.points.iter().enumerate() {
+            data += &format!("{},{},{},{},{},{},{},{}\n", i, p.arc_length, p.position.x, p.position.y,
+                             p.energy, p.perpendicular_force, p.tangent.x, p.tangent.y);
+        }
+        fs::write(file, data)
+    }
+
+    pub fn save_json(&self, file: &str) -> std::io::Result<()> {
+        fs::write(file, serde_json::ser::to_string_pretty(self).unwrap())
+    }
+
+    ///plots the energy over the reaction coordinate. The barrier is marked in blue from the energy of
+    /// the start up to the highest element, the maximum of the smooth profile is the blue point
+    pub fn paint(&self, file: &str) {
+        let energies = self.points.iter().map(|p| p.energy);
+        let (min, max) = energies.fold((f64::MAX, f64::MIN), |(min, max), e| (min.min(e), max.max(e)));
+        let length = self.points[self.points.len() - 1].arc_length;
+
+        let to_plot = |s: f64, e: f64| Image::plot_point(
+            if length > 0.0 { s / length } else { 0.0 },
+            if max > min { (e - min) / (max - min) } else { 0.5 },
+        );
+        let img = Image::plot();
+        let mut buffer = img.canvas();
+
+        // axes
+        let origin = Image::plot_point(0.0, 0.0);
+        img.draw_line(&mut buffer, origin, Image::plot_point(1.0, 0.0), &[0, 0, 0]);
+        img.draw_line(&mut buffer, origin, Image::plot_point(0.0, 1.0), &[0, 0, 0]);
+
+        // the barrier
+        let start = to_plot(0.0, self.points[0].energy);
+        let top = self.points[self.highest];
+        let top = to_plot(top.arc_length, top.energy);
+        img.draw_line(&mut buffer, start, Point { x: top.x, y: start.y }, &[0, 0, 255u8]);
+        img.draw_line(&mut buffer, Point { x: top.x, y: start.y }, top, &[0, 0, 255u8]);
+
+        // the smooth profile through the elements, with its maximum
+        let curve = self.interpolated(10);
+        for pair in curve.windows(2) {
+            img.draw_line(&mut buffer, to_plot(pair[0].0, pair[0].1), to_plot(pair[1].0, pair[1].1), &[0, 255u8, 0]);
+        }
+        for p in &self.points {
+            img.draw_circle(&mut buffer, to_plot(p.arc_length, p.energy), PLOT_POINT_SIZE, &[255u8, 0, 0]);
+        }
+        let maximum = self.interpolated_maximum;
+        img.draw_circle(&mut buffer, to_plot(maximum.arc_length, maximum.energy), PLOT_POINT_SIZE, &[0, 0, 255u8]);
+
+        buffer.save(file).unwrap();
+    }
+}
+
+///cubic Hermite polynomial for the energy between two neighboring elements, matching their energies
+/// and the slopes given by the forces along the path. t runs from 0 to 1 across the segment
+struct Cubic {
+    length: f64,
+    coefficients: [f64; 4],
+}
+
+impl Cubic {
+    fn between(a: ProfilePoint, b: ProfilePoint) -> Self {
+        let length = b.arc_length - a.arc_length;
+        // the slopes with respect to t, the force is the negative slope
+        let m0 = -a.parallel_force * length;
+        let m1 = -b.parallel_force * length;
+        Cubic {
+            length,
+            coefficients: [
+                a.energy,
+                m0,
+                -3.0 * a.energy - 2.0 * m0 + 3.0 * b.energy - m1,
+                2.0 * a.energy + m0 - 2.0 * b.energy + m1,
+            ],
+        }
+    }
+
+    fn at(&self, t: f64) -> f64 {
+        let [d, c, b, a] = self.coefficients;
+        ((a * t + b) * t + c) * t + d
+    }
+
+    ///the places within the segment where the slope vanishes
+    fn extrema(&self) -> Vec<f64> {
+        let [_, c, b, a] = self.coefficients;
+        // 3a t^2 + 2b t + c = 0
+        let roots = if a.abs() < 1e-12 * (b.abs() + c.abs()) {
+            if b == 0.0 { vec![] } else { vec![-c / (2.0 * b)] }
+        } else {
+            let discriminant = b * b - 3.0 * a * c;
+            if discriminant < 0.0 {
+                vec![]
+            } else {
+                let root = discriminant.sqrt();
+                vec![(-b + root) / (3.0 * a), (-b - root) / (3.0 * a)]
+            }
+        };
+        roots.into_iter().filter(|&t| t > 0.0 && t < 1.0).collect()
+    }
+}
+
+///the highest point of the smooth profile, looking at the elements themselves and at every maximum
+/// of the cubic between them
+fn interpolated_maximum(points: &[ProfilePoint]) -> InterpolatedMaximum {
+    let mut maximum = InterpolatedMaximum {
+        position: points[0].position,
+        arc_length: points[0].arc_length,
+        energy: points[0].energy,
+    };
+    for p in points {
+        if p.energy > maximum.energy {
+            maximum = InterpolatedMaximum { position: p.position, arc_length: p.arc_length, energy: p.energy };
+        }
+    }
+    for pair in points.windows(2) {
+        let cubic = Cubic::between(pair[0], pair[1]);
+        for t in cubic.extrema() {
+            let energy = cubic.at(t);
+            if energy > maximum.energy {
+                maximum = InterpolatedMaximum {
+                    position: pair[0].position + t * (pair[1].position - pair[0].position),
+                    arc_length: pair[0].arc_length + t * cubic.length,
+                    energy,
+                };
+            }
+        }
+    }
+    maximum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::{ChainConfig, Method};
+    use crate::optimizer::OptimizerConfig;
+    use crate::minimizer::MinimizerConfig;
+    use crate::muller_brown::{MullerBrown, MINIMA, SADDLES};
+
+    ///a point of the profile E(s) = 1 - (s - 0.3)^2 along the x axis
+    fn parabola(s: f64) -> ProfilePoint {
+        ProfilePoint {
+            position: Point { x: s, y: 0.0 },
+            arc_length: s,
+            energy: 1.0 - (s - 0.3).powi(2),
+            perpendicular_force: 0.0,
+            parallel_force: 2.0 * (s - 0.3),
+            tangent: Point { x: 1.0, y: 0.0 },
+        }
+    }
+
+    #[test]
+    fn hermite_cubic_is_exact_for_a_parabola() {
+        let maximum = interpolated_maximum(&[parabola(0.0), parabola(1.0)]);
+        assert!((maximum.arc_length - 0.3).abs() < 1e-12);
+        assert!((maximum.position.x - 0.3).abs() < 1e-12);
+        assert!((maximum.energy - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn interpolated_maximum_is_closer_to_the_muller_brown_saddle() {
+        let mut chain = Chain::new(ChainConfig {
+            spring_constant: 200.0,
+            pin_ends: true,
+            free_ends: false,
+            relax_ends: false,
+            start: MINIMA[0].0,
+            end: MINIMA[2].0,
+            elements: 10,
+            method: Method::Neb,
+            climbing_image: None,
+            growth_interval: 10,
+            optimizer: OptimizerConfig::Lbfgs { memory: 10, max_step: 0.02 },
+            minimizer: MinimizerConfig::default(),
+            waypoints: vec![],
+            path_file: None,
+            spline: false,
+        });
+        for _ in 0..1000 {
+            chain.iterate(&MullerBrown);
+        }
+
+        let profile = Profile::new(&chain, &MullerBrown);
+        let (position, energy) = SADDLES[0];
+        let highest = profile.points[profile.highest];
+        let maximum = profile.interpolated_maximum;
+        assert!((maximum.energy - energy).abs() < (highest.energy - energy).abs() / 2.0,
+                "interpolated {}, highest element {}", maximum.energy, highest.energy);
+        assert!(maximum.position.distance_sq(position) < highest.position.distance_sq(position));
+    }
+}
@@ -0,0 +1,274 @@
+use std::f64::consts::PI;
+use std::fs;
+use crate::point::Point;
+use crate::pes::PotentialEnergySurface;
+
+const BOLTZMANN: f64 = 1.380649e-23;
+const PLANCK: f64 = 6.62607015e-34;
+const AVOGADRO: f64 = 6.02214076e23;
+const ATOMIC_MASS_UNIT: f64 = 1.66053906660e-27;
+///in cm/s, to turn frequencies into wavenumbers
+const SPEED_OF_LIGHT: f64 = 2.99792458e10;
+
+///what the energies of the PES mean
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnergyUnit {
+    #[default]
+    KjPerMol,
+    KcalPerMol,
+    Ev,
+    Hartree,
+}
+
+impl EnergyUnit {
+    ///the energy of one particle in joule
+    fn joule(&self) -> f64 {
+        match self {
+            EnergyUnit::KjPerMol => 1e3 / AVOGADRO,
+            EnergyUnit::KcalPerMol => 4184.0 / AVOGADRO,
+            EnergyUnit::Ev => 1.602176634e-19,
+            EnergyUnit::Hartree => 4.3597447222071e-18,
+        }
+    }
+}
+
+///what the coordinates of the PES mean
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LengthUnit {
+    #[default]
+    Angstrom,
+    Bohr,
+    Nanometer,
+}
+
+impl LengthUnit {
+    fn meter(&self) -> f64 {
+        match self {
+            LengthUnit::Angstrom => 1e-10,
+            LengthUnit::Bohr => 5.29177210903e-11,
+            LengthUnit::Nanometer => 1e-9,
+        }
+    }
+}
+
+///the mass moving along each coordinate in atomic mass units
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct Masses {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+}
+
+impl Default for Masses {
+    fn default() -> Self {
+        Masses { x: 1.0, y: 1.0 }
+    }
+}
+
+///what is needed to turn the barrier and the curvatures into rate constants
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RateConfig {
+    ///in kelvin
+    pub(crate) temperatures: Vec<f64>,
+    #[serde(default)]
+    pub(crate) masses: Masses,
+    #[serde(default)]
+    pub(crate) energy_unit: EnergyUnit,
+    #[serde(default)]
+    pub(crate) length_unit: LengthUnit,
+    ///if the rates should include the Wigner correction for tunneling
+    #[serde(default)]
+    pub(crate) wigner: bool,
+}
+
+///the rate constants at one temperature, all in 1/s
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct Rate {
+    pub(crate) temperature: f64,
+    ///classical harmonic transition state theory (Vineyard)
+    pub(crate) htst: f64,
+    ///Eyring with quantum harmonic partition functions of the vibrations
+    pub(crate) eyring: f64,
+    ///the Wigner tunneling correction, 1 if it is not used
+    pub(crate) kappa: f64,
+    ///the Eyring rate with the tunneling correction
+    pub(crate) rate: f64,
+}
+
+///a reaction from a minimum over a saddle point, with its rate constants
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Reaction {
+    pub(crate) minimum: Point,
+    pub(crate) saddle: Point,
+    ///in the energy unit of the PES
+    pub(crate) barrier: f64,
+    ///the frequencies of the two vibrations in the minimum, in Hz
+    pub(crate) minimum_frequencies: [f64; 2],
+    ///the frequency of the vibration perpendicular to the reaction coordinate at the saddle, in Hz
+    pub(crate) saddle_frequency: f64,
+    ///the magnitude of the imaginary frequency along the reaction coordinate, in Hz
+    pub(crate) imaginary_frequency: f64,
+    pub(crate) rates: Vec<Rate>,
+    ///activation energy and prefactor in 1/s from a fit of ln(rate) over 1/T, if there are at least
+    /// two temperatures
+    pub(crate) arrhenius: Option<(f64, f64)>,
+}
+
+impl Reaction {
+    ///fails unless the minimum and the saddle point are stationary, that is their gradient norm is at
+    /// most max_gradient, and the hessian has the right number of negative eigenvalues
+    pub fn new(pes: &dyn PotentialEnergySurface, minimum: Point, saddle: Point, config: &RateConfig,
+               max_gradient: f64) -> Result<Self, String> {
+        for p in [minimum, saddle].iter() {
+            let gradient_norm = pes.gradient_at(*p).norm();
+            if gradient_norm > max_gradient || gradient_norm.is_nan() {
+                return Err(format!("({}|{}) is not stationary, its gradient norm is {:e}", p.x, p.y, gradient_norm));
+            }
+        }
+        let minimum_values = frequencies(pes, minimum, config)?;
+        let saddle_values = frequencies(pes, saddle, config)?;
+        if minimum_values[0] <= 0.0 {
+            return Err(format!("({}|{}) is not a minimum", minimum.x, minimum.y));
+        }
+        if saddle_values[0] >= 0.0 || saddle_values[1] <= 0.0 {
+            return Err(format!("({}|{}) is not a first order saddle point", saddle.x, saddle.y));
+        }
+        let minimum_frequencies = [minimum_values[0].sqrt() / (2.0 * PI), minimum_values[1].sqrt() / (2.0 * PI)];
+        let saddle_frequency = saddle_values[1].sqrt() / (2.0 * PI);
+        let imaginary_frequency = (-saddle_values[0]).sqrt() / (2.0 * PI);
+        let barrier = pes.energy_at(saddle) - pes.energy_at(minimum);
+        let barrier_joule = barrier * config.energy_unit.joule();
+
+        let rates: Vec<Rate> = config.temperatures.iter().map(|&temperature| {
+            let kt = BOLTZMANN * temperature;
+            let boltzmann_factor = (-barrier_joule / kt).exp();
+            let htst = minimum_frequencies[0] * minimum_frequencies[1] / saddle_frequency * boltzmann_factor;
+            // vibrational partition functions counted from the bottom of the well
+            let partition = |frequency: f64| 1.0 / (2.0 * (PLANCK * frequency / (2.0 * kt)).sinh());
+            let eyring = kt / PLANCK * partition(saddle_frequency)
+                / (partition(minimum_frequencies[0]) * partition(minimum_frequencies[1])) * boltzmann_factor;
+            let kappa = if config.wigner {
+                1.0 + (PLANCK * imaginary_frequency / kt).powi(2) / 24.0
+            } else {
+                1.0
+            };
+            Rate { temperature, htst, eyring, kappa, rate: kappa * eyring }
+        }).collect();
+
+        Ok(Reaction {
+            minimum,
+            saddle,
+            barrier,
+            minimum_frequencies,
+            saddle_frequency,
+            imaginary_frequency,
+            arrhenius: arrhenius_fit(&rates, config.energy_unit),
+            rates,
+        })
+    }
+}
+
+///the eigenvalues of the mass weighted hessian in 1/s²
+fn frequencies(pes: &dyn PotentialEnergySurface, p: Point, config: &RateConfig) -> Result<[f64; 2], String> {
+    let hessian = pes.hessian_at(p).ok_or("the PES has no hessian")?;
+    let masses = config.masses;
+    let mut weighted = hessian;
+    weighted.xx /= masses.x;
+    weighted.xy /= (masses.x * masses.y).sqrt();
+    weighted.yy /= masses.y;
+    let unit = config.energy_unit.joule() / (config.length_unit.meter().powi(2) * ATOMIC_MASS_UNIT);
+    let (values, _) = weighted.scaled(unit).eigen();
+    Ok(values)
+}
+
+///least squares fit of ln(rate) = ln(prefactor) - activation energy / kT
+fn arrhenius_fit(rates: &[Rate], unit: EnergyUnit) -> Option<(f64, f64)> {
+    if rates.len() < 2 {
+        return None;
+    }
+    let n = rates.len() as f64;
+    let xs: Vec<f64> = rates.iter().map(|r| 1.0 / r.temperature).collect();
+    let ys: Vec<f64> = rates.iter().map(|r| r.rate.ln()).collect();
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let covariance: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    if variance == 0.0 {
+        return None;
+    }
+    let slope = covariance / variance;
+    let activation_energy = -slope * BOLTZMANN / unit.joule();
+    Some((activation_energy, (mean_y - slope * mean_x).exp()))
+}
+
+///in cm⁻¹
+pub fn wavenumber(frequency: f64) -> f64 {
+    frequency / SPEED_OF_LIGHT
+}
+
+///writes the rates of all reactions at all temperatures as one table
+pub fn save_arrhenius_csv(file: &str, reactions: &[(&str, &Reaction)]) -> std::io::Result<()> {
+    let mut data = String::from("direction,temperature,inverse_temperature,htst,eyring,kappa,rate,ln_rate\n");
+    for (name, reaction) in reactions {
+        for r in &reaction.rates {
+            data += &format!("{},{},{},{},{},{},{},{}\n", name, r.temperature, 1.0 / r.temperature,
+                             r.htst, r.eyring, r.kappa, r.rate, r.rate.ln());
+        }
+    }
+    fs::write(file, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::muller_brown::{MullerBrown, MINIMA, SADDLES};
+
+    fn relative_error(value: f64, expected: f64) -> f64 {
+        ((value - expected) / expected).abs()
+    }
+
+    #[test]
+    fn muller_brown_rates_match_the_harmonic_numbers() {
+        let config = RateConfig {
+            temperatures: vec![300.0, 1e5],
+            masses: Masses::default(),
+            energy_unit: EnergyUnit::KjPerMol,
+            length_unit: LengthUnit::Angstrom,
+            wigner: true,
+        };
+        // the rounded reference points are stationary to about 1e-3
+        let reaction = Reaction::new(&MullerBrown, MINIMA[0].0, SADDLES[0].0, &config, 1e-2).unwrap();
+
+        // worked out separately from a finite difference hessian, frequencies in Hz and rates in 1/s
+        assert!(relative_error(reaction.barrier, 106.034674) < 1e-6);
+        assert!(relative_error(reaction.minimum_frequencies[0], 3.22473e13) < 1e-4);
+        assert!(relative_error(reaction.minimum_frequencies[1], 1.015129e14) < 1e-4);
+        assert!(relative_error(reaction.saddle_frequency, 3.52391e13) < 1e-4);
+        assert!(relative_error(reaction.imaginary_frequency, 4.36115e13) < 1e-4);
+        let rate = reaction.rates[0];
+        assert!(relative_error(rate.htst, 3.20681e-5) < 1e-3);
+        assert!(relative_error(rate.eyring, 5.69525e-3) < 1e-3);
+        assert!(relative_error(rate.kappa, 3.02812) < 1e-4);
+        assert!(relative_error(rate.rate, rate.kappa * rate.eyring) < 1e-12);
+
+        // in the classical limit the quantum corrections vanish
+        let hot = reaction.rates[1];
+        assert!(relative_error(hot.eyring, hot.htst) < 1e-3);
+        assert!(relative_error(hot.kappa, 1.0) < 1e-4);
+    }
+
+    #[test]
+    fn the_wrong_points_are_rejected() {
+        let config = RateConfig {
+            temperatures: vec![300.0],
+            masses: Masses::default(),
+            energy_unit: EnergyUnit::default(),
+            length_unit: LengthUnit::default(),
+            wigner: false,
+        };
+        // swapped, and a point on the slope
+        assert!(Reaction::new(&MullerBrown, SADDLES[0].0, MINIMA[0].0, &config, 1e-2).is_err());
+        assert!(Reaction::new(&MullerBrown, Point { x: 0.0, y: 1.0 }, SADDLES[0].0, &config, 1e-2).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::chain::Chain;
+use crate::pes::PotentialEnergySurface;
+use crate::image::ImageConfig;
+use crate::convergence::{self, ConvergenceConfig, Criterion, Progress};
+use crate::stability::{Instability, StabilityConfig};
+
+///what decides how a chain is relaxed
+pub struct Relaxation<'a> {
+    pub(crate) convergence: &'a ConvergenceConfig,
+    pub(crate) convergence_limit: f64,
+    pub(crate) stability: &'a StabilityConfig,
+    ///the area the elements may not leave, if the stability config says so
+    pub(crate) domain: &'a ImageConfig,
+}
+
+///the state of the loop next to the chain itself. It goes into the checkpoints, so a resumed
+/// simulation continues exactly where it stopped
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct LoopState {
+    pub(crate) allowed_energy_rise: f64,
+    ///how many iterations in a row the energy barely changed
+    pub(crate) calm_iterations: usize,
+    ///how many iterations in a row were unstable
+    pub(crate) retries: usize,
+}
+
+impl LoopState {
+    pub fn new(allowed_energy_rise: f64) -> Self {
+        LoopState { allowed_energy_rise, calm_iterations: 0, retries: 0 }
+    }
+}
+
+///what happened in the loop, passed to the callback of Relaxation::run
+pub enum Event<'a> {
+    ///the chain is about to be iterated. Not sent again when an unstable iteration is retried
+    Before(&'a Chain),
+    ///the iteration was unstable, it is retried from the last good chain with a smaller step
+    Retry { iteration: usize, problem: Instability },
+    ///a stable iteration is done
+    Iterated { chain: &'a Chain, energy: f64, progress: &'a Progress<'a>, state: &'a LoopState },
+}
+
+impl<'a> Relaxation<'a> {
+    ///iterates the chain until one of the convergence criteria is met. An unstable iteration is undone
+    /// and retried with a smaller step. If that happens more than max_retries times in a row the chain
+    /// is left at the last good state and the instability is returned
+    pub fn run(&self, chain: &mut Chain, pes: &dyn PotentialEnergySurface, state: &mut LoopState,
+               mut callback: Option<&mut dyn FnMut(Event)>) -> Result<Criterion, Instability> {
+        let mut energy = chain.energy(pes);
+        loop {
+            if state.retries == 0 {
+                if let Some(callback) = callback.as_mut() {
+                    callback(Event::Before(chain));
+                }
+            }
+
+            let last_good = chain.clone();
+            chain.iterate(pes);
+
+            // go back to the last good state with a smaller step if the iteration went wrong
+            if let Some(problem) = self.stability.check(chain, &last_good, pes, self.domain, state.allowed_energy_rise) {
+                state.retries += 1;
+                *chain = last_good;
+                if state.retries > self.stability.max_retries {
+                    return Err(problem);
+                }
+                if let Some(callback) = callback.as_mut() {
+                    callback(Event::Retry { iteration: chain.iteration + 1, problem });
+                }
+                chain.optimizer.reduce_step(self.stability.step_reduction);
+                continue;
+            }
+            state.retries = 0;
+
+            let last_energy = energy;
+            energy = chain.energy(pes);
+            state.calm_iterations = convergence::calm_iterations(state.calm_iterations, last_energy - energy, self.convergence_limit);
+            let forces = chain.perpendicular_forces(pes);
+            let progress = Progress {
+                iteration: chain.iteration,
+                calm_iterations: state.calm_iterations,
+                perpendicular_forces: &forces,
+                max_displacement: convergence::max_displacement(&last_good.elements, &chain.elements),
+                growing: chain.growing.is_some(),
+            };
+            if let Some(callback) = callback.as_mut() {
+                callback(Event::Iterated { chain, energy, progress: &progress, state });
+            }
+
+            if let Some(reason) = self.convergence.check(&progress) {
+                return Ok(reason);
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use crate::point::Point;
+use crate::chain::Chain;
+use crate::image::ImageConfig;
+use crate::pes::PotentialEnergySurface;
+
+///how the simulation watches for and reacts to numerical instabilities
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct StabilityConfig {
+    ///how much the average energy may rise in one iteration. If not given, the energy span of the
+    /// initial chain and the straight line between its ends is used
+    #[serde(default)]
+    pub(crate) max_energy_rise: Option<f64>,
+    ///if elements leaving the area of the image count as an instability
+    #[serde(default = "default_keep_in_domain")]
+    pub(crate) keep_in_domain: bool,
+    ///by how much the step of the optimizer is scaled after an instability
+    #[serde(default = "default_step_reduction")]
+    pub(crate) step_reduction: f64,
+    ///how many times in a row an iteration may be retried before giving up
+    #[serde(default = "default_max_retries")]
+    pub(crate) max_retries: usize,
+}
+
+fn default_keep_in_domain() -> bool {
+    true
+}
+
+fn default_step_reduction() -> f64 {
+    0.5
+}
+
+fn default_max_retries() -> usize {
+    5
+}
+
+impl Default for StabilityConfig {
+    fn default() -> Self {
+        StabilityConfig {
+            max_energy_rise: None,
+            keep_in_domain: default_keep_in_domain(),
+            step_reduction: default_step_reduction(),
+            max_retries: default_max_retries(),
+        }
+    }
+}
+
+///how many points along the straight line between the ends are looked at for the energy span
+const LINE_SAMPLES: usize = 20;
+
+///rises of the average energy below this fraction of the energies are rounding noise
+const RELATIVE_NOISE: f64 = 1e-9;
+
+///what went wrong in an iteration
+#[derive(Debug, Copy, Clone)]
+pub enum Instability {
+    NotANumber { element: usize },
+    EnergyBlowUp { rise: f64 },
+    LeftDomain { element: usize, position: Point },
+}
+
+impl fmt::Display for Instability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instability::NotANumber { element } =>
+                write!(f, "element {} has coordinates that are not a number", element),
+            Instability::EnergyBlowUp { rise } =>
+                write!(f, "the average energy rose by {:e}", rise),
+            Instability::LeftDomain { element, position } =>
+                write!(f, "element {} left the image area to ({}|{})", element, position.x, position.y),
+        }
+    }
+}
+
+impl StabilityConfig {
+    ///the largest rise of the average energy that is still accepted for the given initial chain. It is
+    /// never smaller than the rounding noise of the energies, so a rise of a few ulps is no blow up
+    pub fn allowed_energy_rise(&self, chain: &Chain, pes: &dyn PotentialEnergySurface) -> f64 {
+        // a growing string starts with its ends only, the straight line between them shows the barrier
+        let first = chain.elements[0];
+        let last = chain.elements[chain.elements.len() - 1];
+        let line = (0..=LINE_SAMPLES).map(|i| first + (i as f64 / LINE_SAMPLES as f64) * (last - first));
+        let energies: Vec<f64> = chain.elements.iter().cloned().chain(line).map(|p| pes.energy_at(p)).collect();
+        let (min, max) = energies.iter().fold((f64::MAX, f64::MIN), |(min, max), &e| (min.min(e), max.max(e)));
+        let magnitude = energies.iter().fold(1.0, |m: f64, e| m.max(e.abs()));
+        let noise = RELATIVE_NOISE * magnitude;
+        self.max_energy_rise.unwrap_or(max - min).max(noise)
+    }
+
+    ///looks for anything that went wrong in the iteration from the previous to the current chain
+    pub fn check(&self, chain: &Chain, previous: &Chain, pes: &dyn PotentialEnergySurface,
+                 domain: &ImageConfig, allowed_energy_rise: f64) -> Option<Instability> {
+        for (element, p) in chain.elements.iter().enumerate() {
+            if p.x.is_nan() || p.y.is_nan() {
+                return Some(Instability::NotANumber { element });
+            }
+        }
+        // a growing string changes its average energy by adding elements, that is no instability
+        if chain.elements.len() == previous.elements.len() {
+            let rise = chain.energy(pes) - previous.energy(pes);
+            // a NaN energy can only come from the surface itself, treat it as a blow up as well
+            if rise.is_nan() || rise > allowed_energy_rise {
+                return Some(Instability::EnergyBlowUp { rise });
+            }
+        }
+        if self.keep_in_domain {
+            for (element, &position) in chain.elements.iter().enumerate() {
+                if !domain.contains(position) {
+                    return Some(Instability::LeftDomain { element, position });
+                }
+            }
+        }
+        None
+    }
+}
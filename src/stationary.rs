@@ -0,0 +1,75 @@
+use std::fmt;
+use crate::point::Point;
+use crate::pes::PotentialEnergySurface;
+
+///what kind of stationary point the eigenvalues of the hessian describe, if the point is stationary
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Minimum,
+    FirstOrderSaddle,
+    ///both directions go downhill, a maximum
+    SecondOrderSaddle,
+    ///the gradient is too large for the point to be any of the above
+    NotStationary,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Kind::Minimum => "minimum",
+            Kind::FirstOrderSaddle => "first order saddle point",
+            Kind::SecondOrderSaddle => "second order saddle point (maximum)",
+            Kind::NotStationary => "point that is not stationary",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+///the result of the eigenanalysis of the hessian at a point
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct Characterization {
+    pub(crate) position: Point,
+    pub(crate) energy: f64,
+    ///how far the point is from being stationary
+    pub(crate) gradient_norm: f64,
+    ///in ascending order
+    pub(crate) eigenvalues: [f64; 2],
+    pub(crate) eigenvectors: [Point; 2],
+    pub(crate) kind: Kind,
+}
+
+impl Characterization {
+    ///looks at the hessian at p, None if the surface doesn't provide one. Only points with a gradient
+    /// norm of at most max_gradient are classified by their eigenvalues
+    pub fn at(pes: &dyn PotentialEnergySurface, p: Point, max_gradient: f64) -> Option<Self> {
+        let (eigenvalues, eigenvectors) = pes.hessian_at(p)?.eigen();
+        let gradient_norm = pes.gradient_at(p).norm();
+        let kind = match eigenvalues.iter().filter(|&&value| value < 0.0).count() {
+            _ if gradient_norm > max_gradient || gradient_norm.is_nan() => Kind::NotStationary,
+            0 => Kind::Minimum,
+            1 => Kind::FirstOrderSaddle,
+            _ => Kind::SecondOrderSaddle,
+        };
+        Some(Characterization {
+            position: p,
+            energy: pes.energy_at(p),
+            gradient_norm,
+            eigenvalues,
+            eigenvectors,
+            kind,
+        })
+    }
+}
+
+impl fmt::Display for Characterization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "({:15.10}|{:15.10}) with energy {:15.10} is a {}",
+                 self.position.x, self.position.y, self.energy, self.kind)?;
+        writeln!(f, "    gradient norm: {:e}", self.gradient_norm)?;
+        for (value, vector) in self.eigenvalues.iter().zip(self.eigenvectors.iter()) {
+            writeln!(f, "    eigenvalue: {:15.6e} along ({:10.6}|{:10.6})", value, vector.x, vector.y)?;
+        }
+        Ok(())
+    }
+}